@@ -0,0 +1,152 @@
+//! Measures the cost of node IDs inside `Node` in a simulated cluster.
+//!
+//! `Node` interns the received IDs and clones the full IDs only when it returns actions.
+//! Besides the timings measured by criterion, this counts the clones of large IDs performed by
+//! a simulation run and asserts that they do not exceed the number of IDs in the returned actions.
+//! It also reports the number of heap allocations compared with small IDs.
+use criterion::{criterion_group, criterion_main, Criterion};
+use hyparview::{Action, Node};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const CLUSTER_SIZE: usize = 30;
+const ROUNDS: usize = 10;
+const SWEEPS_PER_ROUND: usize = 10;
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct LargeId {
+    host: String,
+    port: u16,
+    public_key: Vec<u8>,
+}
+impl LargeId {
+    fn new(i: usize) -> Self {
+        LargeId {
+            host: format!("node-{}.example.com", i),
+            port: 3000,
+            public_key: vec![i as u8; 32],
+        }
+    }
+}
+impl Clone for LargeId {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::Relaxed);
+        LargeId {
+            host: self.host.clone(),
+            port: self.port,
+            public_key: self.public_key.clone(),
+        }
+    }
+}
+
+// Runs a simulation and calls `inspect` for every action returned by the nodes.
+//
+// IDs are passed to the nodes by value (never cloned by the simulator itself).
+fn simulate<T, F, G>(make_id: F, mut inspect: G)
+where
+    T: Clone + Eq + Hash,
+    F: Fn(usize) -> T,
+    G: FnMut(&Action<T>),
+{
+    let mut nodes = (0..CLUSTER_SIZE)
+        .map(|i| Node::new(make_id(i), StdRng::seed_from_u64(i as u64)))
+        .collect::<Vec<_>>();
+    let index_of = (0..CLUSTER_SIZE)
+        .map(|i| (make_id(i), i))
+        .collect::<HashMap<_, _>>();
+
+    for node in &mut nodes[1..] {
+        node.join(make_id(0));
+    }
+    for round in 0..ROUNDS {
+        for node in &mut nodes {
+            if round % 2 == 0 {
+                node.shuffle_passive_view();
+            } else {
+                node.sync_active_view();
+            }
+        }
+        // The number of delivery sweeps is bounded because deterministic schedules may livelock.
+        for _ in 0..SWEEPS_PER_ROUND {
+            for i in 0..nodes.len() {
+                let actions = std::iter::from_fn(|| nodes[i].poll_action()).collect::<Vec<_>>();
+                for action in actions {
+                    inspect(&action);
+                    if let Action::Send {
+                        destination,
+                        message,
+                    } = action
+                    {
+                        let j = index_of[&destination];
+                        nodes[j].handle_protocol_message(message);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn bench_ids(c: &mut Criterion) {
+    // Every ID in a returned action is printed as `LargeId { .. }` by `Debug`.
+    let mut materialized = 0;
+    let before = CLONES.load(Ordering::Relaxed);
+    simulate(LargeId::new, |action| {
+        materialized += format!("{:?}", action).matches("LargeId").count();
+    });
+    let clones = CLONES.load(Ordering::Relaxed) - before;
+    assert!(
+        clones <= materialized,
+        "Node cloned IDs internally: clones={}, IDs in actions={}",
+        clones,
+        materialized
+    );
+    println!(
+        "LargeId clones per simulation: {} (IDs in returned actions: {})",
+        clones, materialized
+    );
+
+    let large = count_allocations(|| simulate(LargeId::new, |_| {}));
+    let small = count_allocations(|| simulate(|i| i as u64, |_| {}));
+    println!(
+        "allocations per simulation: LargeId={}, u64={}",
+        large, small
+    );
+
+    let mut group = c.benchmark_group("simulate");
+    group.bench_function("LargeId", |b| b.iter(|| simulate(LargeId::new, |_| {})));
+    group.bench_function("u64", |b| b.iter(|| simulate(|i| i as u64, |_| {})));
+    group.finish();
+}
+
+criterion_group!(benches, bench_ids);
+criterion_main!(benches);
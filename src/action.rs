@@ -4,7 +4,8 @@ use crate::Event;
 /// Actions instructed by HyParView [Node](./struct.Node.html).
 ///
 /// For running HyParView nodes, the users must handle the actions correctly.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action<T> {
     /// Send a message.
     ///
@@ -24,6 +25,18 @@ pub enum Action<T> {
         message: ProtocolMessage<T>,
     },
 
+    /// Send messages to the same destination at once.
+    ///
+    /// This is emitted only by `Node::drain_actions` if `Node::enable_send_batching` has been called.
+    /// The messages must be delivered in order, as if they were sent by consecutive `Send` actions.
+    SendBatch {
+        /// The ID of the destination node of the messages.
+        destination: T,
+
+        /// Outgoing messages.
+        messages: Vec<ProtocolMessage<T>>,
+    },
+
     /// Close a connection.
     ///
     /// The connection between the local node and `node` must be disconnected.
@@ -50,6 +63,13 @@ impl<T> Action<T> {
         }
     }
 
+    pub(crate) fn send_batch(destination: T, messages: Vec<ProtocolMessage<T>>) -> Self {
+        Action::SendBatch {
+            destination,
+            messages,
+        }
+    }
+
     pub(crate) fn disconnect(node: T) -> Self {
         Action::Disconnect { node }
     }
@@ -65,4 +85,123 @@ impl<T> Action<T> {
             event: Event::NeighborDown { node },
         }
     }
+
+    /// Converts the node IDs in the action by `f`.
+    pub(crate) fn map<U, F>(self, mut f: F) -> Action<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        match self {
+            Action::Send {
+                destination,
+                message,
+            } => Action::send(f(destination), message.map(f)),
+            Action::SendBatch {
+                destination,
+                messages,
+            } => {
+                let destination = f(destination);
+                let messages = messages.into_iter().map(|m| m.map(&mut f)).collect();
+                Action::send_batch(destination, messages)
+            }
+            Action::Disconnect { node } => Action::disconnect(f(node)),
+            Action::Notify { event } => Action::Notify {
+                event: event.map(f),
+            },
+        }
+    }
+}
+
+// Returns the node whose connection is affected by `action`.
+fn connection<T>(action: &Action<T>) -> Option<&T> {
+    match action {
+        Action::Send { destination, .. } => Some(destination),
+        Action::SendBatch { destination, .. } => Some(destination),
+        Action::Disconnect { node } => Some(node),
+        Action::Notify { .. } => None,
+    }
+}
+
+/// Removes redundant actions from `actions`.
+///
+/// The following actions are removed:
+/// - `Send` actions identical to the previous `Send` action to the same node
+/// - `Disconnect` actions following another `Disconnect` action for the same node
+/// - `NEIGHBOR` messages followed by a `Disconnect` action for the destination
+pub(crate) fn remove_redundant_actions<T: Eq>(actions: &mut Vec<Action<T>>) {
+    let mut keep = vec![true; actions.len()];
+    for i in 0..actions.len() {
+        let node = match connection(&actions[i]) {
+            None => continue,
+            Some(node) => node,
+        };
+        let previous = (0..i)
+            .rev()
+            .filter(|&j| keep[j])
+            .find(|&j| connection(&actions[j]) == Some(node));
+        keep[i] = match (&actions[i], previous.map(|j| &actions[j])) {
+            (Action::Send { message, .. }, Some(Action::Send { message: m, .. })) => message != m,
+            (Action::Disconnect { .. }, Some(Action::Disconnect { .. })) => false,
+            _ => true,
+        };
+        if let (Action::Disconnect { .. }, true) = (&actions[i], keep[i]) {
+            for j in (0..i).rev() {
+                match &actions[j] {
+                    Action::Send {
+                        destination,
+                        message: ProtocolMessage::Neighbor(_),
+                    } if destination == node => keep[j] = false,
+                    Action::Disconnect { node: n } if n == node => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    actions.retain(|_| keep.next().unwrap_or(true));
+}
+
+/// Packs the `Send` actions in `actions` into `SendBatch` actions per destination.
+///
+/// Each batch is placed at the position of its first message.
+/// A `Disconnect` action for the destination closes the batch,
+/// so that messages are never moved across it.
+pub(crate) fn batch_sends<T: Eq>(actions: Vec<Action<T>>) -> Vec<Action<T>> {
+    let mut batched: Vec<Action<T>> = Vec::with_capacity(actions.len());
+    let mut open_batches: Vec<usize> = Vec::new();
+    for action in actions {
+        match action {
+            Action::Send {
+                destination,
+                message,
+            } => {
+                let open = open_batches
+                    .iter()
+                    .copied()
+                    .find(|&i| connection(&batched[i]) == Some(&destination));
+                if let Some(Action::SendBatch { messages, .. }) = open.map(|i| &mut batched[i]) {
+                    messages.push(message);
+                } else {
+                    open_batches.push(batched.len());
+                    batched.push(Action::send_batch(destination, vec![message]));
+                }
+            }
+            Action::Disconnect { node } => {
+                open_batches.retain(|&i| connection(&batched[i]) != Some(&node));
+                batched.push(Action::disconnect(node));
+            }
+            action => batched.push(action),
+        }
+    }
+    batched
+        .into_iter()
+        .map(|action| match action {
+            Action::SendBatch {
+                destination,
+                mut messages,
+            } if messages.len() == 1 => Action::send(destination, messages.remove(0)),
+            action => action,
+        })
+        .collect()
 }
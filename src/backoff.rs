@@ -0,0 +1,119 @@
+use crate::view::IndexedVec;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Options for the exponential backoff of unreachable peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct BackoffOptions {
+    /// Backoff duration after the first failure.
+    ///
+    /// The duration is doubled by each consecutive failure.
+    #[cfg_attr(feature = "serde", serde(with = "crate::seconds"))]
+    pub initial_backoff: Duration,
+
+    /// Upper limit of backoff durations.
+    #[cfg_attr(feature = "serde", serde(with = "crate::seconds"))]
+    pub max_backoff: Duration,
+
+    /// Number of consecutive failures after which a peer is removed from the passive view.
+    ///
+    /// If it is zero, peers are never removed due to failures.
+    pub max_failures: u32,
+}
+impl BackoffOptions {
+    /// The default value of `initial_backoff` field.
+    pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+    /// The default value of `max_backoff` field.
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// The default value of `max_failures` field.
+    pub const DEFAULT_MAX_FAILURES: u32 = 5;
+
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 1u32.checked_shl(failures.saturating_sub(1));
+        factor
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+impl Default for BackoffOptions {
+    fn default() -> Self {
+        BackoffOptions {
+            initial_backoff: Self::DEFAULT_INITIAL_BACKOFF,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            max_failures: Self::DEFAULT_MAX_FAILURES,
+        }
+    }
+}
+
+/// Failure state of an unreachable peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureState {
+    /// Number of consecutive failures.
+    pub consecutive_failures: u32,
+
+    /// Time (on the clock advanced by `Node::advance_clock`) at which the peer becomes
+    /// eligible for promotion again.
+    pub retry_at: Duration,
+}
+
+#[derive(Debug)]
+pub(crate) struct FailureMemory<T> {
+    states: IndexedVec<T, (T, FailureState)>,
+}
+impl<T> FailureMemory<T>
+where
+    T: Clone + Eq + Hash,
+{
+    pub fn new() -> Self {
+        FailureMemory {
+            states: IndexedVec::with_key(0, |x| &x.0),
+        }
+    }
+
+    pub fn get(&self, node: &T) -> Option<&FailureState> {
+        self.states.get(node).map(|x| &x.1)
+    }
+
+    pub fn is_backing_off(&self, node: &T, now: Duration) -> bool {
+        self.get(node).is_some_and(|s| now < s.retry_at)
+    }
+
+    /// Records a failure and returns the updated state.
+    pub fn record_failure(
+        &mut self,
+        node: T,
+        options: &BackoffOptions,
+        now: Duration,
+    ) -> &FailureState {
+        let i = if let Some(i) = self.states.position(&node) {
+            i
+        } else {
+            let state = FailureState {
+                consecutive_failures: 0,
+                retry_at: now,
+            };
+            self.states.push((node, state));
+            self.states.len() - 1
+        };
+        let state = &mut self.states.get_index_mut(i).expect("never fails").1;
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        state.retry_at = now.saturating_add(options.backoff(state.consecutive_failures));
+        state
+    }
+
+    pub fn record_success(&mut self, node: &T) {
+        if let Some(i) = self.states.position(node) {
+            self.states.swap_remove(i);
+        }
+    }
+
+    /// Forgets the peers that have not failed for a while.
+    pub fn prune(&mut self, options: &BackoffOptions, now: Duration) {
+        self.states
+            .retain(|x| now < x.1.retry_at.saturating_add(options.max_backoff));
+    }
+}
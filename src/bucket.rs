@@ -0,0 +1,72 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// A trait for grouping node IDs into buckets of the passive view.
+///
+/// See the documentation of `Node::enable_passive_view_buckets` method.
+pub trait GroupKey {
+    /// Returns the key of the group to which the node belongs (e.g., the prefix of its IP address).
+    fn group_key(&self) -> u64;
+}
+
+/// Members of the passive view grouped by their keys.
+///
+/// The buckets must be updated by `insert` and `remove` whenever the passive view changes.
+#[derive(Debug)]
+pub(crate) struct PassiveViewBuckets<T> {
+    group_key: fn(&T) -> u64,
+    max_bucket_size: usize,
+    buckets: BTreeMap<u64, Vec<T>>,
+}
+impl<T: GroupKey> PassiveViewBuckets<T> {
+    pub fn new(max_bucket_size: u16) -> Self {
+        PassiveViewBuckets {
+            group_key: T::group_key,
+            max_bucket_size: usize::from(max_bucket_size.max(1)),
+            buckets: BTreeMap::new(),
+        }
+    }
+}
+impl<T> PassiveViewBuckets<T>
+where
+    T: Clone + Eq,
+{
+    pub fn insert(&mut self, node: &T) {
+        let key = (self.group_key)(node);
+        self.buckets.entry(key).or_default().push(node.clone());
+    }
+
+    pub fn remove(&mut self, node: &T) {
+        let key = (self.group_key)(node);
+        if let Some(bucket) = self.buckets.get_mut(&key) {
+            if let Some(i) = bucket.iter().position(|n| n == node) {
+                bucket.swap_remove(i);
+            }
+            if bucket.is_empty() {
+                self.buckets.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the member of the passive view that should be evicted for adding `node`.
+    ///
+    /// If the bucket of `node` is full, a member of the bucket is selected.
+    /// Otherwise, if `is_view_full` is `true`, a member of the largest bucket is selected.
+    pub fn select_eviction<R: Rng>(
+        &self,
+        node: Option<&T>,
+        is_view_full: bool,
+        rng: &mut R,
+    ) -> Option<&T> {
+        let bucket = node
+            .and_then(|node| self.buckets.get(&(self.group_key)(node)))
+            .filter(|bucket| bucket.len() >= self.max_bucket_size);
+        let bucket = match bucket {
+            Some(bucket) => bucket,
+            None if is_view_full => self.buckets.values().max_by_key(|b| b.len())?,
+            None => return None,
+        };
+        bucket.choose(rng)
+    }
+}
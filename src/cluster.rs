@@ -0,0 +1,245 @@
+//! Cluster-wide invariant checks over a set of HyParView nodes.
+//!
+//! Per-node checks (`Node::check_invariants`) can't detect asymmetric links or partitions.
+//! [ClusterReport](./struct.ClusterReport.html) analyzes the active views of a set of nodes
+//! (e.g., the nodes of a simulator or snapshots collected from a running cluster) instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use hyparview::cluster::{ClusterReport, ClusterThresholds, NodeSnapshot};
+//!
+//! let snapshots = vec![
+//!     NodeSnapshot { id: "foo", active_view: vec!["bar"], passive_view: vec![] },
+//!     NodeSnapshot { id: "bar", active_view: vec!["foo"], passive_view: vec![] },
+//!     NodeSnapshot { id: "baz", active_view: vec!["foo"], passive_view: vec![] },
+//! ];
+//! let report = ClusterReport::new(&snapshots, Some(&"foo"));
+//! assert_eq!(report.asymmetric_edges, [("baz", "foo")]);
+//! assert_eq!(report.unreachable_nodes, ["baz"]);
+//! assert_eq!(report.components.len(), 1);
+//!
+//! let thresholds = ClusterThresholds { max_asymmetric_edges: 1, max_unreachable_nodes: 1, ..Default::default() };
+//! assert!(report.check(&thresholds).is_ok());
+//! assert!(report.check(&ClusterThresholds::default()).is_err());
+//! ```
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+
+/// Snapshot of the views of a node.
+///
+/// It can be taken by `Node::snapshot` method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeSnapshot<T> {
+    /// The ID of the node.
+    pub id: T,
+
+    /// The active view of the node.
+    pub active_view: Vec<T>,
+
+    /// The passive view of the node.
+    pub passive_view: Vec<T>,
+}
+
+/// Result of the cluster-wide analysis of the active views.
+///
+/// Active view entries referring to nodes that are not contained in the snapshots are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterReport<T> {
+    /// The number of the analyzed nodes.
+    pub nodes: usize,
+
+    /// Pairs `(a, b)` such that `b` is in the active view of `a` but `a` is not in the active view of `b`.
+    pub asymmetric_edges: Vec<(T, T)>,
+
+    /// Nodes having empty active views.
+    pub isolated_nodes: Vec<T>,
+
+    /// Connected components of the (undirected) graph formed by the active views.
+    ///
+    /// The components are sorted in descending order of their sizes.
+    pub components: Vec<Vec<T>>,
+
+    /// Nodes unreachable from the root node by following the active views.
+    ///
+    /// If no root is given, this is empty.
+    pub unreachable_nodes: Vec<T>,
+}
+impl<T> ClusterReport<T>
+where
+    T: Clone + Eq + Hash,
+{
+    /// Analyzes the active views in `snapshots`.
+    ///
+    /// If `root` is `Some(_)`, the nodes unreachable from it are also reported.
+    pub fn new(snapshots: &[NodeSnapshot<T>], root: Option<&T>) -> Self {
+        let index = snapshots
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (&s.id, i))
+            .collect::<HashMap<_, _>>();
+        let edges = snapshots
+            .iter()
+            .map(|s| {
+                s.active_view
+                    .iter()
+                    .filter_map(|n| index.get(n).copied())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut asymmetric_edges = Vec::new();
+        let mut undirected = edges.clone();
+        for (a, peers) in edges.iter().enumerate() {
+            for &b in peers {
+                if !edges[b].contains(&a) {
+                    asymmetric_edges.push((snapshots[a].id.clone(), snapshots[b].id.clone()));
+                    undirected[b].push(a);
+                }
+            }
+        }
+
+        let isolated_nodes = snapshots
+            .iter()
+            .filter(|s| s.active_view.is_empty())
+            .map(|s| s.id.clone())
+            .collect();
+
+        let mut component_of = vec![None; snapshots.len()];
+        let mut components = Vec::new();
+        for start in 0..snapshots.len() {
+            if component_of[start].is_some() {
+                continue;
+            }
+            let reached = traverse(&undirected, start);
+            for &i in &reached {
+                component_of[i] = Some(components.len());
+            }
+            components.push(
+                reached
+                    .into_iter()
+                    .map(|i| snapshots[i].id.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+
+        let mut unreachable_nodes = Vec::new();
+        if let Some(root) = root {
+            let mut reachable = vec![false; snapshots.len()];
+            if let Some(&start) = index.get(root) {
+                for i in traverse(&edges, start) {
+                    reachable[i] = true;
+                }
+            }
+            unreachable_nodes = snapshots
+                .iter()
+                .zip(reachable)
+                .filter(|(_, reachable)| !reachable)
+                .map(|(s, _)| s.id.clone())
+                .collect();
+        }
+
+        ClusterReport {
+            nodes: snapshots.len(),
+            asymmetric_edges,
+            isolated_nodes,
+            components,
+            unreachable_nodes,
+        }
+    }
+}
+impl<T: fmt::Debug> ClusterReport<T> {
+    /// Checks whether the report is within `thresholds`.
+    ///
+    /// If not, returns an error message describing the exceeded thresholds.
+    pub fn check(&self, thresholds: &ClusterThresholds) -> Result<(), String> {
+        let mut errors = Vec::new();
+        if self.asymmetric_edges.len() > thresholds.max_asymmetric_edges {
+            errors.push(format!(
+                "{} asymmetric active edges (max: {}): {:?}",
+                self.asymmetric_edges.len(),
+                thresholds.max_asymmetric_edges,
+                self.asymmetric_edges
+            ));
+        }
+        if self.isolated_nodes.len() > thresholds.max_isolated_nodes {
+            errors.push(format!(
+                "{} nodes with empty active views (max: {}): {:?}",
+                self.isolated_nodes.len(),
+                thresholds.max_isolated_nodes,
+                self.isolated_nodes
+            ));
+        }
+        if self.components.len() > thresholds.max_components {
+            errors.push(format!(
+                "{} connected components (max: {}): {:?}",
+                self.components.len(),
+                thresholds.max_components,
+                self.components
+            ));
+        }
+        if self.unreachable_nodes.len() > thresholds.max_unreachable_nodes {
+            errors.push(format!(
+                "{} nodes unreachable from the root (max: {}): {:?}",
+                self.unreachable_nodes.len(),
+                thresholds.max_unreachable_nodes,
+                self.unreachable_nodes
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+/// Thresholds for `ClusterReport::check` method.
+///
+/// The default values require a fully symmetric and connected cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterThresholds {
+    /// Maximum number of asymmetric active edges.
+    pub max_asymmetric_edges: usize,
+
+    /// Maximum number of nodes having empty active views.
+    pub max_isolated_nodes: usize,
+
+    /// Maximum number of connected components.
+    pub max_components: usize,
+
+    /// Maximum number of nodes unreachable from the root node.
+    pub max_unreachable_nodes: usize,
+}
+impl Default for ClusterThresholds {
+    fn default() -> Self {
+        ClusterThresholds {
+            max_asymmetric_edges: 0,
+            max_isolated_nodes: 0,
+            max_components: 1,
+            max_unreachable_nodes: 0,
+        }
+    }
+}
+
+// Returns the indices of the nodes reachable from `start` (including itself).
+fn traverse(edges: &[Vec<usize>], start: usize) -> Vec<usize> {
+    let mut visited = vec![false; edges.len()];
+    let mut reached = Vec::new();
+    let mut queue = VecDeque::new();
+    visited[start] = true;
+    queue.push_back(start);
+    while let Some(i) = queue.pop_front() {
+        reached.push(i);
+        for &j in &edges[i] {
+            if !visited[j] {
+                visited[j] = true;
+                queue.push_back(j);
+            }
+        }
+    }
+    reached
+}
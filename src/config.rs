@@ -0,0 +1,260 @@
+//! Loading of HyParView configurations from TOML files.
+//!
+//! A configuration section consists of [NodeOptions](../struct.NodeOptions.html) (`options` table),
+//! the intervals of the periodic maintenance methods of [Node](../struct.Node.html) (`intervals` table)
+//! and the contact nodes used for joining the cluster (`seeds` array).
+//! Omitted keys take their default values, and unknown keys are rejected.
+//! All durations (e.g., `options.tombstone_lifetime`) are specified in seconds (fractions are allowed).
+//!
+//! This module is available only if the `config` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use hyparview::config::Config;
+//! use std::time::Duration;
+//!
+//! let text = r#"
+//! [gossip]
+//! seeds = ["10.0.0.1:5000", "10.0.0.2:5000"]
+//!
+//! [gossip.options]
+//! max_active_view_size = 5
+//! max_passive_view_size = 30
+//!
+//! [gossip.intervals]
+//! shuffle_passive_view = 30
+//! sync_active_view = 2.5
+//! "#;
+//!
+//! let config = Config::<String>::from_toml_str(text, Some("gossip")).unwrap();
+//! assert_eq!(config.options.max_active_view_size, 5);
+//! assert_eq!(config.intervals.sync_active_view, Duration::from_millis(2500));
+//! assert_eq!(config.seeds.len(), 2);
+//!
+//! let error = Config::<String>::from_toml_str("[options]\nmax_active_view_size = 0", None).unwrap_err();
+//! assert_eq!(error.key(), Some("options.max_active_view_size"));
+//! ```
+use crate::NodeOptions;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Configuration of a HyParView node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config<T> {
+    /// The options of the node.
+    pub options: NodeOptions,
+
+    /// The intervals of the periodic maintenance.
+    pub intervals: MaintenanceIntervals,
+
+    /// The contact nodes given to `Node::join` method.
+    pub seeds: Vec<T>,
+}
+impl<T> Config<T> {
+    /// Validates the configuration.
+    ///
+    /// The keys in the resulting errors are relative to the configuration section.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Err(e) = self.options.validate() {
+            return Err(ConfigError::invalid(
+                format!("options.{}", e.field()),
+                e.to_string(),
+            ));
+        }
+        self.intervals.validate()
+    }
+}
+impl<T> Config<T>
+where
+    T: DeserializeOwned,
+{
+    /// Parses and validates the configuration in `section` of a TOML document.
+    ///
+    /// `section` is a dot-separated path of tables (e.g., `"services.gossip"`).
+    /// If it is `None`, the whole document is regarded as the configuration.
+    pub fn from_toml_str(text: &str, section: Option<&str>) -> Result<Self, ConfigError> {
+        let config: Self = match section {
+            None => toml::from_str(text).map_err(ConfigError::Parse)?,
+            Some(section) => {
+                let mut value: toml::Value = toml::from_str(text).map_err(ConfigError::Parse)?;
+                for name in section.split('.') {
+                    value = value
+                        .as_table_mut()
+                        .and_then(|t| t.remove(name))
+                        .ok_or_else(|| ConfigError::MissingSection(section.to_owned()))?;
+                }
+                value.try_into().map_err(ConfigError::Parse)?
+            }
+        };
+        config.validate().map_err(|e| e.with_section(section))?;
+        Ok(config)
+    }
+
+    /// Reads the TOML file at `path` and parses the configuration in `section`.
+    ///
+    /// See `Config::from_toml_str` for the details.
+    pub fn from_toml_file<P: AsRef<Path>>(
+        path: P,
+        section: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml_str(&text, section)
+    }
+}
+impl<T> Default for Config<T> {
+    fn default() -> Self {
+        Config {
+            options: NodeOptions::default(),
+            intervals: MaintenanceIntervals::default(),
+            seeds: Vec::new(),
+        }
+    }
+}
+
+/// Intervals at which the periodic maintenance methods of [Node](../struct.Node.html) should be called.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceIntervals {
+    /// Interval of `Node::shuffle_passive_view` calls.
+    #[serde(with = "crate::seconds")]
+    pub shuffle_passive_view: Duration,
+
+    /// Interval of `Node::fill_active_view` calls.
+    #[serde(with = "crate::seconds")]
+    pub fill_active_view: Duration,
+
+    /// Interval of `Node::sync_active_view` calls.
+    #[serde(with = "crate::seconds")]
+    pub sync_active_view: Duration,
+
+    /// Interval of `Node::optimize_active_view` calls.
+    #[serde(with = "crate::seconds")]
+    pub optimize_active_view: Duration,
+
+    /// Interval of `Node::probe_passive_view` calls.
+    ///
+    /// `None` means that passive view probing is disabled.
+    #[serde(with = "crate::seconds::option")]
+    pub probe_passive_view: Option<Duration>,
+}
+impl MaintenanceIntervals {
+    /// The default value of `shuffle_passive_view` field.
+    pub const DEFAULT_SHUFFLE_PASSIVE_VIEW: Duration = Duration::from_secs(10);
+
+    /// The default value of `fill_active_view` field.
+    pub const DEFAULT_FILL_ACTIVE_VIEW: Duration = Duration::from_secs(1);
+
+    /// The default value of `sync_active_view` field.
+    pub const DEFAULT_SYNC_ACTIVE_VIEW: Duration = Duration::from_secs(60);
+
+    /// The default value of `optimize_active_view` field.
+    pub const DEFAULT_OPTIMIZE_ACTIVE_VIEW: Duration = Duration::from_secs(60);
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let intervals = [
+            ("shuffle_passive_view", Some(self.shuffle_passive_view)),
+            ("fill_active_view", Some(self.fill_active_view)),
+            ("sync_active_view", Some(self.sync_active_view)),
+            ("optimize_active_view", Some(self.optimize_active_view)),
+            ("probe_passive_view", self.probe_passive_view),
+        ];
+        for (name, interval) in intervals.iter() {
+            if *interval == Some(Duration::from_secs(0)) {
+                return Err(ConfigError::invalid(
+                    format!("intervals.{}", name),
+                    "the interval must be positive".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+impl Default for MaintenanceIntervals {
+    fn default() -> Self {
+        MaintenanceIntervals {
+            shuffle_passive_view: Self::DEFAULT_SHUFFLE_PASSIVE_VIEW,
+            fill_active_view: Self::DEFAULT_FILL_ACTIVE_VIEW,
+            sync_active_view: Self::DEFAULT_SYNC_ACTIVE_VIEW,
+            optimize_active_view: Self::DEFAULT_OPTIMIZE_ACTIVE_VIEW,
+            probe_passive_view: None,
+        }
+    }
+}
+
+/// Error of loading a configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Io(io::Error),
+
+    /// The TOML document is malformed or doesn't match the configuration schema.
+    ///
+    /// The error message contains the offending key (relative to the configuration section) if it is known.
+    Parse(toml::de::Error),
+
+    /// The configuration section was not found in the TOML document.
+    MissingSection(String),
+
+    /// The configuration is inconsistent.
+    Invalid {
+        /// The (dot-separated) path of the offending key.
+        key: String,
+
+        /// The reason of the error.
+        reason: String,
+    },
+}
+impl ConfigError {
+    /// Returns the offending key if the configuration is inconsistent.
+    pub fn key(&self) -> Option<&str> {
+        if let ConfigError::Invalid { key, .. } = self {
+            Some(key)
+        } else {
+            None
+        }
+    }
+
+    fn invalid(key: String, reason: String) -> Self {
+        ConfigError::Invalid { key, reason }
+    }
+
+    fn with_section(self, section: Option<&str>) -> Self {
+        match (self, section) {
+            (ConfigError::Invalid { key, reason }, Some(section)) => {
+                ConfigError::invalid(format!("{}.{}", section, key), reason)
+            }
+            (e, _) => e,
+        }
+    }
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read the configuration file: {}", e),
+            ConfigError::Parse(e) => write!(f, "cannot parse the configuration: {}", e),
+            ConfigError::MissingSection(section) => {
+                write!(f, "no such configuration section: {:?}", section)
+            }
+            ConfigError::Invalid { key, reason } => {
+                write!(f, "invalid value for key `{}`: {}", key, reason)
+            }
+        }
+    }
+}
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 /// Events emitted by HyParView [Node](./struct.Node.html).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event<T> {
     /// New neighbor node arrived.
     ///
@@ -17,3 +18,15 @@ pub enum Event<T> {
         node: T,
     },
 }
+impl<T> Event<T> {
+    /// Converts the node ID in the event by `f`.
+    pub(crate) fn map<U, F>(self, f: F) -> Event<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Event::NeighborUp { node } => Event::NeighborUp { node: f(node) },
+            Event::NeighborDown { node } => Event::NeighborDown { node: f(node) },
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Violation of an invariant of the state of HyParView [Node](./struct.Node.html).
+///
+/// See `Node::check_invariants` method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation<T> {
+    /// The active view contains the ID of the node itself.
+    SelfInActiveView,
+
+    /// The passive view contains the ID of the node itself.
+    SelfInPassiveView,
+
+    /// The active view contains the same node more than once.
+    DuplicateInActiveView(T),
+
+    /// The passive view contains the same node more than once.
+    DuplicateInPassiveView(T),
+
+    /// The node is contained in both the active and passive views.
+    InBothViews(T),
+
+    /// The active view has more nodes than `NodeOptions::max_active_view_size`.
+    ActiveViewOverflow {
+        /// The number of nodes in the active view.
+        size: usize,
+
+        /// The maximum size of the active view.
+        max_size: usize,
+    },
+
+    /// The passive view has more nodes than `NodeOptions::max_passive_view_size`.
+    PassiveViewOverflow {
+        /// The number of nodes in the passive view.
+        size: usize,
+
+        /// The maximum size of the passive view.
+        max_size: usize,
+    },
+}
+impl<T: fmt::Debug> fmt::Display for InvariantViolation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantViolation::SelfInActiveView => write!(f, "self in the active view"),
+            InvariantViolation::SelfInPassiveView => write!(f, "self in the passive view"),
+            InvariantViolation::DuplicateInActiveView(n) => {
+                write!(f, "duplicate node {:?} in the active view", n)
+            }
+            InvariantViolation::DuplicateInPassiveView(n) => {
+                write!(f, "duplicate node {:?} in the passive view", n)
+            }
+            InvariantViolation::InBothViews(n) => {
+                write!(f, "node {:?} in both the active and passive views", n)
+            }
+            InvariantViolation::ActiveViewOverflow { size, max_size } => {
+                write!(f, "the active view has {} nodes (max: {})", size, max_size)
+            }
+            InvariantViolation::PassiveViewOverflow { size, max_size } => {
+                write!(f, "the passive view has {} nodes (max: {})", size, max_size)
+            }
+        }
+    }
+}
@@ -7,25 +7,61 @@
 //! [HyParView]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
 #![warn(missing_docs)]
 pub use action::Action;
+pub use backoff::{BackoffOptions, FailureState};
+pub use bucket::GroupKey;
 pub use event::Event;
+pub use invariant::InvariantViolation;
+pub use metrics::NodeMetrics;
 pub use node::Node;
-pub use node_options::NodeOptions;
+pub use node_options::{NodeOptions, NodeOptionsBuilder, NodeOptionsError};
+pub use probe::ProbeOptions;
+pub use rate_limit::{RateLimit, RateLimitOptions, RateLimitPolicy, RateLimitStats};
+pub use reputation::{ReputationEvent, ReputationWeighting, ReputationWeights};
+pub use shared_id::{IdInterner, Ids, IdsIter, SharedId};
 pub use ttl::TimeToLive;
+pub use xbot::{LinkCostOracle, XbotOptions};
+pub use zone::ZoneId;
+
+#[macro_use]
+mod trace;
 
 mod action;
+mod backoff;
+mod bucket;
 mod event;
+mod invariant;
+mod metrics;
 mod node;
 mod node_options;
+mod probe;
+mod rate_limit;
+mod reputation;
+#[cfg(feature = "serde")]
+mod seconds;
+mod shared_id;
+mod tombstone;
 mod ttl;
+mod view;
+mod xbot;
+mod zone;
 
+pub mod cluster;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod message;
+#[cfg(test)]
+mod model_check;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "replay")]
+pub mod replay;
 
 #[cfg(test)]
 mod tests {
-    use rand;
     use rand::rngs::ThreadRng;
     use std::collections::HashSet;
     use std::hash::Hash;
+    use std::time::Duration;
 
     use super::message::ProtocolMessage;
     use super::*;
@@ -46,7 +82,10 @@ mod tests {
         node.join("bar");
 
         let action = assert_some!(node.poll_action());
-        assert_eq!(action, Action::send("bar", ProtocolMessage::join(&"foo")));
+        assert_eq!(
+            action,
+            Action::send("bar", ProtocolMessage::join(&"foo", None, false))
+        );
         assert!(node.poll_action().is_none());
     }
 
@@ -160,6 +199,1436 @@ mod tests {
         }
     }
 
+    #[test]
+    fn xbot_replaces_costly_link() {
+        let options = NodeOptions {
+            max_active_view_size: 1,
+            ..Default::default()
+        };
+        let mut nodes = vec![
+            Node::with_options("foo", rand::thread_rng(), options.clone()),
+            Node::with_options("bar", rand::thread_rng(), options.clone()),
+            Node::with_options("baz", rand::thread_rng(), options.clone()),
+            Node::with_options("qux", rand::thread_rng(), options.clone()),
+        ];
+        let xbot_options = XbotOptions {
+            unbiased_neighbors: 0,
+            ..Default::default()
+        };
+        for node in &mut nodes {
+            if *node.id() == "foo" {
+                node.enable_xbot(
+                    |n: &&str| if *n == "baz" { 1 } else { 10 },
+                    xbot_options.clone(),
+                );
+            } else {
+                node.enable_xbot(|_: &&str| 1, xbot_options.clone());
+            }
+        }
+
+        // foo <-> bar, baz <-> qux
+        nodes[1].join("foo");
+        nodes[3].join("baz");
+        deliver_messages(&mut nodes);
+        nodes[0].handle_protocol_message(
+            message::ShuffleReplyMessage {
+                sender: "bar",
+                sender_incarnation: 0,
+                nodes: vec!["baz"],
+                zones: Vec::new(),
+                incarnations: Vec::new(),
+                tombstones: Vec::new(),
+            }
+            .into(),
+        );
+        assert_eq!(nodes[0].active_view(), ["bar"]);
+        assert_eq!(nodes[0].passive_view(), ["baz"]);
+
+        // foo <-> baz, qux <-> bar
+        nodes[0].optimize_active_view();
+        deliver_messages(&mut nodes);
+        assert_eq!(nodes[0].active_view(), ["baz"]);
+        assert_eq!(nodes[1].active_view(), ["qux"]);
+        assert_eq!(nodes[2].active_view(), ["foo"]);
+        assert_eq!(nodes[3].active_view(), ["bar"]);
+    }
+
+    #[test]
+    fn xbot_disconnects_old_node() {
+        use crate::cluster::ClusterReport;
+
+        let options = NodeOptions {
+            max_active_view_size: 1,
+            ..Default::default()
+        };
+        let mut nodes = vec![
+            Node::with_options("foo", rand::thread_rng(), options.clone()),
+            Node::with_options("bar", rand::thread_rng(), options.clone()),
+            Node::with_options("baz", rand::thread_rng(), options.clone()),
+        ];
+        let xbot_options = XbotOptions {
+            unbiased_neighbors: 0,
+            ..Default::default()
+        };
+        nodes[0].enable_xbot(|n: &&str| if *n == "baz" { 1 } else { 10 }, xbot_options);
+
+        // foo <-> bar, baz has room for foo
+        nodes[1].join("foo");
+        deliver_messages(&mut nodes);
+        nodes[0].handle_protocol_message(
+            message::ShuffleReplyMessage {
+                sender: "bar",
+                sender_incarnation: 0,
+                nodes: vec!["baz"],
+                zones: Vec::new(),
+                incarnations: Vec::new(),
+                tombstones: Vec::new(),
+            }
+            .into(),
+        );
+        assert_eq!(nodes[0].active_view(), ["bar"]);
+        assert!(nodes[2].active_view().is_empty());
+
+        nodes[0].optimize_active_view();
+        deliver_messages(&mut nodes);
+        assert_eq!(nodes[0].active_view(), ["baz"]);
+        assert_eq!(nodes[2].active_view(), ["foo"]);
+        assert!(!nodes[1].active_view().contains(&"foo"));
+
+        let snapshots = nodes.iter().map(|n| n.snapshot()).collect::<Vec<_>>();
+        let report = ClusterReport::new(&snapshots, None);
+        assert!(report.asymmetric_edges.is_empty());
+
+        // Duplicate or late replies that match no outstanding optimization are ignored
+        for sender in &["baz", "qux"] {
+            nodes[0].handle_protocol_message(
+                message::OptimizationReplyMessage {
+                    sender: *sender,
+                    sender_incarnation: 0,
+                    old_node: "baz",
+                    accepted: true,
+                }
+                .into(),
+            );
+        }
+        assert_eq!(nodes[0].active_view(), ["baz"]);
+        let mut disconnected = Vec::new();
+        while let Some(action) = nodes[0].poll_action() {
+            if let Action::Disconnect { node } = action {
+                disconnected.push(node);
+            }
+        }
+        assert_eq!(disconnected, ["qux"]);
+    }
+
+    #[test]
+    fn zone_quota_works() {
+        let options = NodeOptions {
+            max_active_view_size: 2,
+            max_local_zone_active_view_size: Some(1),
+            ..Default::default()
+        };
+        let local = Some(ZoneId::new(0));
+        let remote = Some(ZoneId::new(1));
+
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.set_zone(local);
+        for &(sender, zone) in &[("bar", local), ("baz", local), ("qux", remote)] {
+            node.handle_protocol_message(ProtocolMessage::join(&sender, zone, false));
+        }
+        assert_eq!(to_set(node.active_view()), to_set(&["baz", "qux"]));
+        assert_eq!(node.passive_view(), ["bar"]);
+        assert_eq!(node.zone_of(&"bar"), local);
+
+        // "bar" is not promoted because the quota of the local zone has been reached
+        while node.poll_action().is_some() {}
+        node.disconnect(&"qux", false);
+        while let Some(action) = node.poll_action() {
+            if let Action::Send { destination, .. } = action {
+                assert_ne!(destination, "bar");
+            }
+        }
+        assert_eq!(node.active_view(), ["baz"]);
+        assert_eq!(node.zone_of(&"qux"), None);
+
+        // The last hop of a `FORWARD_JOIN` random walk is selected from the remote zones
+        node.handle_protocol_message(ProtocolMessage::join(&"qux", remote, false));
+        while node.poll_action().is_some() {}
+        let mut forwarded = 0;
+        for _ in 0..10 {
+            let message =
+                ProtocolMessage::forward_join(&"bar", "quux", local, 0, TimeToLive::new(1));
+            node.handle_protocol_message(message);
+            while let Some(action) = node.poll_action() {
+                if let Action::Send {
+                    destination,
+                    message: ProtocolMessage::ForwardJoin(_),
+                } = action
+                {
+                    assert_eq!(destination, "qux");
+                    forwarded += 1;
+                }
+            }
+        }
+        assert_eq!(forwarded, 10);
+    }
+
+    #[test]
+    fn passive_view_buckets_work() {
+        let options = NodeOptions {
+            max_passive_view_size: 4,
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.enable_passive_view_buckets(2);
+        node.handle_protocol_message(
+            message::ShuffleReplyMessage {
+                sender: "bar",
+                sender_incarnation: 0,
+                nodes: vec!["a1", "a2", "a3", "b1", "c1", "c2"],
+                zones: Vec::new(),
+                incarnations: Vec::new(),
+                tombstones: Vec::new(),
+            }
+            .into(),
+        );
+
+        let buckets = node
+            .passive_view()
+            .iter()
+            .map(|n| &n[..1])
+            .collect::<Vec<_>>();
+        assert_eq!(buckets.len(), 4);
+        assert_eq!(buckets.iter().filter(|&&b| b == "a").count(), 1);
+        assert_eq!(buckets.iter().filter(|&&b| b == "b").count(), 1);
+        assert_eq!(buckets.iter().filter(|&&b| b == "c").count(), 2);
+
+        // Shrinking the view also evicts a member of the largest bucket
+        let options = NodeOptions {
+            max_passive_view_size: 3,
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        let mut buckets = node
+            .passive_view()
+            .iter()
+            .map(|n| &n[..1])
+            .collect::<Vec<_>>();
+        buckets.sort();
+        assert_eq!(buckets, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn rate_limit_works() {
+        let mut options = NodeOptions::default();
+        options.rate_limits.join = Some(RateLimit {
+            burst: 1,
+            refill_interval: Duration::from_secs(1),
+        });
+        options.rate_limits.policy = RateLimitPolicy::TryLater;
+        let mut nodes = vec![
+            Node::with_options("foo", rand::thread_rng(), options),
+            Node::new("bar", rand::thread_rng()),
+        ];
+
+        nodes[1].join("foo");
+        nodes[1].join("foo");
+        deliver_messages(&mut nodes);
+        assert_eq!(nodes[1].active_view(), ["foo"]);
+        assert_eq!(nodes[0].rate_limit_stats().shed_joins, 1);
+
+        // "bar" retries joining after the duration requested by `TRY_LATER` message
+        nodes[1].advance_clock(Duration::from_millis(500));
+        assert!(nodes[1].poll_action().is_none());
+
+        nodes[1].advance_clock(Duration::from_millis(500));
+        let action = assert_some!(nodes[1].poll_action());
+        assert_eq!(
+            action,
+            Action::send("foo", ProtocolMessage::join(&"bar", None, false))
+        );
+
+        // Messages from new senders are discarded while the buckets are full
+        let mut options = NodeOptions::default();
+        options.rate_limits.join = Some(RateLimit {
+            burst: 1,
+            refill_interval: Duration::from_secs(1),
+        });
+        options.rate_limits.max_buckets = 1;
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        node.handle_protocol_message(ProtocolMessage::join(&"baz", None, false));
+        assert_eq!(node.active_view(), ["bar"]);
+        assert_eq!(node.rate_limit_stats().shed_joins, 1);
+
+        node.advance_clock(Duration::from_secs(1));
+        node.handle_protocol_message(ProtocolMessage::join(&"baz", None, false));
+        assert_eq!(to_set(node.active_view()), to_set(&["bar", "baz"]));
+
+        // Unlimited kinds don't need buckets
+        let mut options = NodeOptions::default();
+        options.rate_limits.join = Some(RateLimit {
+            burst: 1,
+            refill_interval: Duration::from_secs(0),
+        });
+        options.rate_limits.max_buckets = 0;
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        assert_eq!(node.active_view(), ["bar"]);
+        assert_eq!(node.rate_limit_stats().shed_joins, 0);
+    }
+
+    #[test]
+    fn join_redirect_works() {
+        let options = NodeOptions {
+            join_redirect_limit: Some(RateLimit {
+                burst: 1,
+                refill_interval: Duration::from_secs(60),
+            }),
+            ..Default::default()
+        };
+        let mut nodes = vec![
+            Node::with_options("foo", rand::thread_rng(), options),
+            Node::new("bar", rand::thread_rng()),
+            Node::new("baz", rand::thread_rng()),
+        ];
+
+        nodes[1].join("foo");
+        deliver_messages(&mut nodes);
+        assert_eq!(nodes[0].active_view(), ["bar"]);
+
+        // "foo" has absorbed too many joins, so the join of "baz" is redirected to "bar"
+        nodes[2].join("foo");
+        let action = assert_some!(nodes[2].poll_action());
+        if let Action::Send { message, .. } = action {
+            nodes[0].handle_protocol_message(message);
+        }
+        let action = assert_some!(nodes[0].poll_action());
+        assert_eq!(
+            action,
+            Action::send("baz", ProtocolMessage::join_redirect(&"foo", vec!["bar"]))
+        );
+
+        nodes[2].handle_protocol_message(ProtocolMessage::join_redirect(&"foo", vec!["bar"]));
+        let action = assert_some!(nodes[2].poll_action());
+        assert_eq!(
+            action,
+            Action::send("bar", ProtocolMessage::join(&"baz", None, true))
+        );
+    }
+
+    impl GroupKey for &'static str {
+        fn group_key(&self) -> u64 {
+            u64::from(self.as_bytes()[0])
+        }
+    }
+
+    #[test]
+    fn join_accept_seeds_passive_view() {
+        let mut nodes = vec![
+            Node::new("foo", rand::thread_rng()),
+            Node::new("bar", rand::thread_rng()),
+            Node::new("baz", rand::thread_rng()),
+        ];
+        nodes[1].join("foo");
+        deliver_messages(&mut nodes);
+
+        nodes[2].join("foo");
+        let action = assert_some!(nodes[2].poll_action());
+        if let Action::Send { message, .. } = action {
+            nodes[0].handle_protocol_message(message);
+        }
+        assert_some!(nodes[0].poll_action()); // `Notify`
+        let action = assert_some!(nodes[0].poll_action());
+        assert_eq!(
+            action,
+            Action::send(
+                "baz",
+                ProtocolMessage::join_accept(&"foo", None, vec!["bar"], vec![None], vec![0])
+            )
+        );
+        if let Action::Send { message, .. } = action {
+            nodes[2].handle_protocol_message(message);
+        }
+        assert_eq!(nodes[2].active_view(), ["foo"]);
+        assert_eq!(nodes[2].passive_view(), ["bar"]);
+    }
+
+    #[test]
+    fn incarnation_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        let mut join = ProtocolMessage::join(&"bar", None, false);
+        join.set_sender_incarnation(1);
+        node.handle_protocol_message(join);
+        assert_eq!(node.active_view(), ["bar"]);
+        assert_eq!(node.incarnation_of(&"bar"), Some(1));
+        while node.poll_action().is_some() {}
+
+        // A message sent by an older incarnation is ignored
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", false));
+        assert_eq!(node.active_view(), ["bar"]);
+        assert!(node.poll_action().is_none());
+
+        // A newer incarnation replaces the old connection
+        let mut join = ProtocolMessage::join(&"bar", None, false);
+        join.set_sender_incarnation(2);
+        node.handle_protocol_message(join);
+        assert_eq!(node.active_view(), ["bar"]);
+        assert_eq!(node.incarnation_of(&"bar"), Some(2));
+        assert_eq!(
+            assert_some!(node.poll_action()),
+            Action::Disconnect { node: "bar" }
+        );
+        while node.poll_action().is_some() {}
+
+        // A `FORWARD_JOIN` message for a newer incarnation also replaces the old connection
+        let message = ProtocolMessage::forward_join(&"baz", "bar", None, 5, TimeToLive::new(0));
+        node.handle_protocol_message(message);
+        assert_eq!(node.active_view(), ["bar"]);
+        assert_eq!(node.incarnation_of(&"bar"), Some(5));
+        let actions = std::iter::from_fn(|| node.poll_action()).collect::<Vec<_>>();
+        assert_eq!(actions[0], Action::Disconnect { node: "bar" });
+        assert!(actions.contains(&Action::send(
+            "bar",
+            ProtocolMessage::neighbor(&"foo", None, true)
+        )));
+    }
+
+    #[test]
+    fn tombstone_works() {
+        let options = NodeOptions {
+            tombstone_lifetime: Some(Duration::from_secs(10)),
+            max_gossiped_tombstones: 1,
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", false));
+        assert!(node.active_view().is_empty());
+
+        // "bar" is not resurrected by shuffling
+        let reply = message::ShuffleReplyMessage {
+            sender: "baz",
+            sender_incarnation: 0,
+            nodes: vec!["bar", "qux"],
+            zones: Vec::new(),
+            incarnations: Vec::new(),
+            tombstones: Vec::new(),
+        };
+        node.handle_protocol_message(reply.clone().into());
+        assert_eq!(node.passive_view(), ["qux"]);
+
+        // The tombstone is gossiped
+        node.handle_protocol_message(ProtocolMessage::join(&"baz", None, false));
+        while node.poll_action().is_some() {}
+        node.shuffle_passive_view();
+        let action = assert_some!(node.poll_action());
+        if let Action::Send {
+            message: ProtocolMessage::Shuffle(m),
+            ..
+        } = action
+        {
+            assert_eq!(m.tombstones.len(), 1);
+            assert_eq!(m.tombstones[0].node, "bar");
+        } else {
+            panic!("{:?}", action);
+        }
+
+        // The tombstone expires
+        node.advance_clock(Duration::from_secs(10));
+        node.handle_protocol_message(reply.into());
+        assert_eq!(to_set(node.passive_view()), to_set(&["bar", "qux"]));
+
+        // Received tombstones are capped per message and in total
+        let options = NodeOptions {
+            tombstone_lifetime: Some(Duration::from_secs(10)),
+            max_gossiped_tombstones: 2,
+            max_tombstones: 3,
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        let tombstones = |nodes: &[(&'static str, u64)]| {
+            nodes
+                .iter()
+                .map(|&(node, lifetime)| message::Tombstone {
+                    node,
+                    incarnation: 0,
+                    lifetime: Duration::from_secs(lifetime),
+                })
+                .collect::<Vec<_>>()
+        };
+        let reply = |nodes, tombstones| message::ShuffleReplyMessage {
+            sender: "baz",
+            sender_incarnation: 0,
+            nodes,
+            zones: Vec::new(),
+            incarnations: Vec::new(),
+            tombstones,
+        };
+        let ts = tombstones(&[("a", 5), ("b", 6), ("c", 7)]);
+        node.handle_protocol_message(reply(Vec::new(), ts).into());
+        let ts = tombstones(&[("d", 8), ("e", 9)]);
+        node.handle_protocol_message(reply(Vec::new(), ts).into());
+        node.handle_protocol_message(reply(vec!["a", "b", "c", "d", "e"], Vec::new()).into());
+        assert_eq!(to_set(node.passive_view()), to_set(&["a", "c"]));
+    }
+
+    #[test]
+    fn passive_view_probing_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.enable_passive_view_probing(ProbeOptions {
+            probes_per_round: 2,
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        });
+        node.handle_protocol_message(
+            message::ShuffleReplyMessage {
+                sender: "bar",
+                sender_incarnation: 0,
+                nodes: vec!["baz", "qux"],
+                zones: Vec::new(),
+                incarnations: Vec::new(),
+                tombstones: Vec::new(),
+            }
+            .into(),
+        );
+        while node.poll_action().is_some() {}
+
+        node.probe_passive_view();
+        let mut probed = Vec::new();
+        while let Some(action) = node.poll_action() {
+            if let Action::Send {
+                destination,
+                message: ProtocolMessage::Ping(_),
+            } = action
+            {
+                probed.push(destination);
+            }
+        }
+        assert_eq!(to_set(&probed), to_set(&["baz", "qux"]));
+
+        // Only "baz" replies (the connection is closed without `DISCONNECT` message)
+        node.handle_protocol_message(
+            message::PongMessage {
+                sender: "baz",
+                sender_incarnation: 0,
+            }
+            .into(),
+        );
+        assert_eq!(node.poll_action(), Some(Action::disconnect("baz")));
+        assert_eq!(node.poll_action(), None);
+        node.advance_clock(Duration::from_secs(5));
+        assert_eq!(node.passive_view(), ["baz"]);
+
+        // A pending probe is cancelled if the node leaves the passive view
+        node.probe_passive_view();
+        while node.poll_action().is_some() {}
+        node.handle_protocol_message(ProtocolMessage::neighbor(&"baz", None, true));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"baz", true));
+        while node.poll_action().is_some() {}
+        assert_eq!(node.passive_view(), ["baz"]);
+        node.advance_clock(Duration::from_secs(5));
+        assert_eq!(node.passive_view(), ["baz"]);
+
+        // A pending probe is cancelled if the node is re-added with a fresh incarnation
+        node.probe_passive_view();
+        while node.poll_action().is_some() {}
+        node.handle_protocol_message(
+            message::ShuffleReplyMessage {
+                sender: "bar",
+                sender_incarnation: 0,
+                nodes: vec!["baz"],
+                zones: Vec::new(),
+                incarnations: vec![1],
+                tombstones: Vec::new(),
+            }
+            .into(),
+        );
+        while node.poll_action().is_some() {}
+        node.advance_clock(Duration::from_secs(5));
+        assert_eq!(node.passive_view(), ["baz"]);
+
+        // Probed nodes reply without changing their views
+        let mut probed = Node::new("baz", rand::thread_rng());
+        probed.handle_protocol_message(
+            message::PingMessage {
+                sender: "foo",
+                sender_incarnation: 0,
+            }
+            .into(),
+        );
+        assert_eq!(
+            probed.poll_action(),
+            Some(Action::send("foo", ProtocolMessage::pong(&"baz")))
+        );
+        assert_eq!(probed.poll_action(), Some(Action::disconnect("foo")));
+        assert_eq!(probed.poll_action(), None);
+        assert!(probed.passive_view().is_empty());
+    }
+
+    #[test]
+    fn failure_backoff_works() {
+        let options = NodeOptions {
+            failure_backoff: BackoffOptions {
+                initial_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(60),
+                max_failures: 3,
+            },
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", true));
+        assert_eq!(node.passive_view(), ["bar"]);
+
+        node.report_unreachable(&"bar");
+        assert!(node.poll_action().is_none());
+        let state = assert_some!(node.failure_state_of(&"bar"));
+        assert_eq!(state.consecutive_failures, 1);
+        assert_eq!(state.retry_at, Duration::from_secs(1));
+
+        // The backoff duration is doubled
+        node.advance_clock(Duration::from_secs(1));
+        node.fill_active_view();
+        let action = assert_some!(node.poll_action());
+        assert_eq!(
+            action,
+            Action::send("bar", ProtocolMessage::neighbor(&"foo", None, true))
+        );
+        node.report_unreachable(&"bar");
+        let state = assert_some!(node.failure_state_of(&"bar"));
+        assert_eq!(state.retry_at, Duration::from_secs(3));
+
+        // "bar" is dropped after three failures
+        node.report_unreachable(&"bar");
+        assert!(node.passive_view().is_empty());
+    }
+
+    #[test]
+    fn reputation_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.enable_reputation(ReputationWeights::default());
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", true));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"baz", true));
+        node.record_reputation_event(&"bar", ReputationEvent::InvalidMessage);
+        assert_eq!(node.reputation_of(&"bar"), Some(-60));
+        assert_eq!(node.reputation_of(&"qux"), None);
+
+        // The disreputable node is not promoted
+        for _ in 0..10 {
+            node.fill_active_view();
+            let action = assert_some!(node.poll_action());
+            assert_eq!(
+                action,
+                Action::send("baz", ProtocolMessage::neighbor(&"foo", None, true))
+            );
+        }
+
+        // Uptime in the active view increases the score
+        node.handle_protocol_message(ProtocolMessage::neighbor(&"baz", None, true));
+        node.advance_clock(Duration::from_millis(1500));
+        assert_eq!(node.reputation_of(&"baz"), Some(1));
+        while node.poll_action().is_some() {}
+
+        // A pending `NEIGHBOR` request is not regarded as rejected until `DISCONNECT` is received
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"qux", true));
+        node.fill_active_view();
+        node.fill_active_view();
+        assert_eq!(node.reputation_of(&"qux"), Some(0));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"qux", true));
+        assert_eq!(node.reputation_of(&"qux"), Some(-5));
+
+        // Exceeding the rate limits is penalized lightly
+        let mut options = NodeOptions::default();
+        options.rate_limits.forward_join = Some(RateLimit {
+            burst: 1,
+            refill_interval: Duration::from_secs(1),
+        });
+        node.apply_options(options).unwrap();
+        for _ in 0..2 {
+            let message =
+                ProtocolMessage::forward_join(&"baz", "quux", None, 0, TimeToLive::new(3));
+            node.handle_protocol_message(message);
+        }
+        assert_eq!(node.reputation_of(&"baz"), Some(-1));
+
+        // Scores are kept after the peers are evicted from the views
+        let options = NodeOptions {
+            max_passive_view_size: 1,
+            shuffle_passive_view_size: 1,
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        assert_eq!(node.passive_view(), ["qux"]);
+        assert_eq!(node.reputation_of(&"bar"), Some(-60));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", true));
+        assert_eq!(node.reputation_of(&"bar"), Some(-60));
+
+        // The score closest to zero is evicted if the table is full
+        let options = NodeOptions {
+            max_reputation_scores: 2,
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        assert_eq!(node.reputation_of(&"bar"), Some(-60));
+        assert_eq!(node.reputation_of(&"qux"), Some(-5));
+        assert_eq!(node.reputation_of(&"baz"), Some(0));
+    }
+
+    #[test]
+    fn metrics_works() {
+        let options = NodeOptions {
+            max_active_view_size: 1,
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        node.handle_protocol_message(ProtocolMessage::join(&"baz", None, false));
+        while node.poll_action().is_some() {}
+
+        let metrics = node.metrics();
+        assert_eq!(metrics.received(message::MessageKind::Join), 2);
+        assert_eq!(metrics.sent(message::MessageKind::JoinAccept), 2);
+        assert_eq!(metrics.sent(message::MessageKind::Disconnect), 1);
+        assert_eq!(metrics.active_view_evictions, 1);
+        assert_eq!(metrics.active_view_size, 1);
+        assert_eq!(metrics.passive_view_size, 1);
+
+        node.reset_metrics();
+        let metrics = node.metrics();
+        assert_eq!(metrics.received(message::MessageKind::Join), 0);
+        assert_eq!(metrics.active_view_size, 1);
+    }
+
+    #[test]
+    fn check_invariants_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.enable_invariant_checks();
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        node.handle_protocol_message(ProtocolMessage::join(&"baz", None, false));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"qux", true));
+        assert_eq!(node.check_invariants(), []);
+
+        // Shrinking the views via the options doesn't evict nodes immediately
+        node.options_mut().max_active_view_size = 1;
+        node.options_mut().max_passive_view_size = 0;
+        assert_eq!(
+            node.check_invariants(),
+            [
+                InvariantViolation::ActiveViewOverflow {
+                    size: 2,
+                    max_size: 1
+                },
+                InvariantViolation::PassiveViewOverflow {
+                    size: 1,
+                    max_size: 0
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_options_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        for n in &["a", "b", "c", "d"] {
+            node.handle_protocol_message(ProtocolMessage::join(n, None, false));
+        }
+        for n in &["e", "f", "g"] {
+            node.handle_protocol_message(ProtocolMessage::disconnect(n, true));
+        }
+        while node.poll_action().is_some() {}
+
+        // Invalid options are rejected
+        let options = NodeOptions {
+            max_active_view_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            node.apply_options(options).err(),
+            Some(NodeOptionsError::ZeroActiveViewSize)
+        );
+        assert_eq!(node.options().max_active_view_size, 4);
+
+        // Shrinking
+        let options = NodeOptions {
+            max_active_view_size: 2,
+            max_passive_view_size: 2,
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        assert_eq!(node.active_view().len(), 2);
+        assert_eq!(node.passive_view().len(), 2);
+        assert_eq!(node.check_invariants(), []);
+
+        let mut disconnects = 0;
+        let mut downs = 0;
+        while let Some(action) = node.poll_action() {
+            match action {
+                Action::Send { message, .. } => {
+                    assert_eq!(message.kind(), message::MessageKind::Disconnect);
+                    disconnects += 1;
+                }
+                Action::Notify {
+                    event: Event::NeighborDown { .. },
+                } => downs += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(disconnects, 2);
+        assert_eq!(downs, 2);
+
+        // Growing: a promotion is requested for each free slot
+        let options = NodeOptions {
+            max_active_view_size: 4,
+            max_passive_view_size: 2,
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        let mut destinations = Vec::new();
+        while let Some(action) = node.poll_action() {
+            if let Action::Send {
+                destination,
+                message,
+            } = action
+            {
+                assert!(node.passive_view().contains(&destination));
+                assert_eq!(message.kind(), message::MessageKind::Neighbor);
+                destinations.push(destination);
+            } else {
+                panic!("unexpected action: {:?}", action);
+            }
+        }
+        assert_eq!(to_set(&destinations), to_set(node.passive_view()));
+
+        // Lowered zone quotas are enforced
+        let local = Some(ZoneId::new(0));
+        let remote = Some(ZoneId::new(1));
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.set_zone(local);
+        for &(sender, zone) in &[("a", local), ("b", local), ("c", local), ("d", remote)] {
+            node.handle_protocol_message(ProtocolMessage::join(&sender, zone, false));
+        }
+        while node.poll_action().is_some() {}
+
+        let options = NodeOptions {
+            max_local_zone_active_view_size: Some(1),
+            ..Default::default()
+        };
+        node.apply_options(options).unwrap();
+        assert_eq!(node.active_view().len(), 2);
+        assert!(node.active_view().contains(&"d"));
+        assert_eq!(node.metrics().active_view_evictions, 2);
+        assert_eq!(node.check_invariants(), []);
+
+        // The evicted local nodes are not promoted again
+        while let Some(action) = node.poll_action() {
+            if let Action::Send {
+                destination,
+                message,
+            } = action
+            {
+                assert_ne!(message.kind(), message::MessageKind::Neighbor);
+                assert!(!node.active_view().contains(&destination));
+            }
+        }
+    }
+
+    #[test]
+    fn node_options_builder_works() {
+        let options = NodeOptionsBuilder::for_cluster_size(1000).build().unwrap();
+        assert_eq!(options.max_active_view_size, 4);
+        assert_eq!(options.max_passive_view_size, 24);
+        assert_eq!(options.active_random_walk_len, 5);
+        assert_eq!(options.passive_random_walk_len, 2);
+
+        // Presets for small clusters are also valid
+        for cluster_size in 0..=10 {
+            let options = NodeOptionsBuilder::for_cluster_size(cluster_size)
+                .build()
+                .unwrap();
+            assert!(options.shuffle_active_view_size <= options.max_active_view_size);
+        }
+
+        let error = NodeOptionsBuilder::new()
+            .active_random_walk_len(2)
+            .passive_random_walk_len(3)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            NodeOptionsError::PassiveRandomWalkLenTooLarge {
+                passive_random_walk_len: 3,
+                active_random_walk_len: 2
+            }
+        );
+        assert_eq!(error.field(), "passive_random_walk_len");
+
+        let error = NodeOptionsBuilder::new()
+            .max_passive_view_size(1)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.field(), "shuffle_passive_view_size");
+
+        let error = NodeOptionsBuilder::new()
+            .max_local_zone_active_view_size(Some(0))
+            .build()
+            .unwrap_err();
+        assert_eq!(error, NodeOptionsError::ZeroLocalZoneQuota);
+
+        // Every violation is reported
+        let options = NodeOptions {
+            max_active_view_size: 4,
+            max_local_zone_active_view_size: Some(1),
+            max_remote_zone_active_view_size: Some(2),
+            tombstone_lifetime: Some(NodeOptions::MAX_DURATION * 2),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.errors(),
+            [
+                NodeOptionsError::ZoneQuotasTooSmall {
+                    max_local_zone_active_view_size: 1,
+                    max_remote_zone_active_view_size: 2,
+                    max_active_view_size: 4
+                },
+                NodeOptionsError::DurationTooLarge {
+                    field: "tombstone_lifetime",
+                    duration: NodeOptions::MAX_DURATION * 2
+                }
+            ]
+        );
+        assert_eq!(options.validate(), Err(options.errors()[0].clone()));
+    }
+
+    #[test]
+    fn large_durations_work() {
+        let options = NodeOptions {
+            tombstone_lifetime: Some(NodeOptions::MAX_DURATION),
+            failure_backoff: BackoffOptions {
+                initial_backoff: NodeOptions::MAX_DURATION,
+                max_backoff: NodeOptions::MAX_DURATION,
+                max_failures: 3,
+            },
+            ..Default::default()
+        };
+        let mut node = Node::with_options("foo", rand::thread_rng(), options);
+        node.enable_passive_view_probing(ProbeOptions {
+            timeout: Duration::MAX,
+            ..Default::default()
+        });
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar", true));
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"baz", true));
+        node.join("qux");
+        node.handle_protocol_message(ProtocolMessage::try_later(
+            &"qux",
+            message::MessageKind::Join,
+            Duration::MAX,
+        ));
+        node.advance_clock(Duration::MAX);
+        node.report_unreachable(&"bar");
+        node.probe_passive_view();
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"baz", false));
+        node.advance_clock(Duration::MAX);
+        assert_eq!(node.check_invariants(), []);
+    }
+
+    #[test]
+    fn large_views_work() {
+        let options = NodeOptions {
+            max_active_view_size: 300,
+            max_passive_view_size: 1000,
+            active_random_walk_len: 300,
+            ..Default::default()
+        };
+        let mut node = Node::with_options(0u32, rand::thread_rng(), options);
+        for i in 1..=1200 {
+            node.handle_protocol_message(ProtocolMessage::disconnect(&i, true));
+        }
+        for i in 2001..=2400 {
+            node.handle_protocol_message(ProtocolMessage::join(&i, None, false));
+        }
+        while node.poll_action().is_some() {}
+        assert_eq!(node.active_view().len(), 300);
+        assert_eq!(node.passive_view().len(), 1000);
+        assert_eq!(node.check_invariants(), []);
+
+        node.shuffle_passive_view();
+        let action = assert_some!(node.poll_action());
+        if let Action::Send {
+            message: ProtocolMessage::Shuffle(m),
+            ..
+        } = action
+        {
+            assert_eq!(m.ttl, TimeToLive::new(300));
+        } else {
+            panic!("unexpected action: {:?}", action);
+        }
+    }
+
+    #[test]
+    fn node_is_send_and_sync() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        fn assert_send_and_sync<T: Send + Sync>(_: &T) {}
+
+        let mut node = Node::new(0u32, StdRng::seed_from_u64(0));
+        node.enable_xbot(|_: &u32| 1, XbotOptions::default());
+        node.enable_reputation(ReputationWeights::default());
+        assert_send_and_sync(&node);
+    }
+
+    #[test]
+    fn indexed_views_work() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let options = NodeOptions {
+            max_active_view_size: 5,
+            max_passive_view_size: 30,
+            ..Default::default()
+        };
+        let mut node = Node::with_options(0u32, StdRng::seed_from_u64(1), options);
+
+        // The indices agree with the views after random operations
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..1000 {
+            let peer = rng.gen_range(1, 60);
+            match rng.gen_range(0, 6) {
+                0 => node.handle_protocol_message(ProtocolMessage::join(&peer, None, false)),
+                1 => node.handle_protocol_message(ProtocolMessage::disconnect(&peer, true)),
+                2 => node.handle_protocol_message(ProtocolMessage::disconnect(&peer, false)),
+                3 => node.handle_protocol_message(ProtocolMessage::neighbor(&peer, None, true)),
+                4 => node.shuffle_passive_view(),
+                _ => node.fill_active_view(),
+            }
+            while node.poll_action().is_some() {}
+            assert_eq!(node.check_invariants(), []);
+            for peer in 1..60 {
+                let in_views =
+                    node.active_view().contains(&peer) || node.passive_view().contains(&peer);
+                assert_eq!(node.incarnation_of(&peer).is_some(), in_views);
+            }
+        }
+    }
+
+    #[test]
+    fn shared_ids_work() {
+        let mut interner = IdInterner::new();
+        let foo = interner.intern("foo".to_owned());
+        let bar = interner.intern("bar".to_owned());
+        assert!(SharedId::ptr_eq(&foo, &interner.intern("foo".to_owned())));
+        assert_eq!(interner.len(), 2);
+
+        let mut node = Node::new(foo.clone(), rand::thread_rng());
+        node.handle_protocol_message(ProtocolMessage::join(&bar, None, false));
+        assert!(SharedId::ptr_eq(&node.active_view()[0], &bar));
+
+        let destinations = std::iter::from_fn(|| node.poll_action())
+            .filter_map(|action| match action {
+                Action::Send { destination, .. } => Some(destination),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(destinations.len(), 1);
+        assert!(SharedId::ptr_eq(&destinations[0], &bar));
+        drop(destinations);
+
+        drop(foo);
+        interner.purge();
+        assert_eq!(interner.len(), 2); // Both are still referred from `node`
+        drop(node);
+        drop(bar);
+        interner.purge();
+        assert!(interner.is_empty());
+
+        // `Node` interns the received IDs by itself
+        let mut node = Node::new("foo".to_owned(), rand::thread_rng());
+        node.handle_protocol_message(ProtocolMessage::join(&"bar".to_owned(), None, false));
+        let bar = node.active_view().shared_ids()[0].clone();
+        node.handle_protocol_message(ProtocolMessage::disconnect(&"bar".to_owned(), true));
+        assert!(node.active_view().is_empty());
+        assert!(SharedId::ptr_eq(&node.passive_view().shared_ids()[0], &bar));
+    }
+
+    #[test]
+    fn action_coalescing_works() {
+        use crate::action::{batch_sends, remove_redundant_actions};
+
+        let join = ProtocolMessage::join(&"foo", None, false);
+        let neighbor = ProtocolMessage::neighbor(&"foo", None, true);
+        let disconnect = ProtocolMessage::disconnect(&"foo", true);
+
+        let mut actions = vec![
+            Action::send("bar", neighbor.clone()),
+            Action::send("bar", neighbor.clone()),
+            Action::send("baz", neighbor.clone()),
+            Action::send("bar", disconnect.clone()),
+            Action::disconnect("bar"),
+            Action::disconnect("bar"),
+            Action::send("baz", join.clone()),
+            Action::send("bar", join.clone()),
+        ];
+        remove_redundant_actions(&mut actions);
+        assert_eq!(
+            actions,
+            [
+                Action::send("baz", neighbor.clone()),
+                Action::send("bar", disconnect.clone()),
+                Action::disconnect("bar"),
+                Action::send("baz", join.clone()),
+                Action::send("bar", join.clone()),
+            ]
+        );
+        assert_eq!(
+            batch_sends(actions),
+            [
+                Action::send_batch("baz", vec![neighbor, join.clone()]),
+                Action::send("bar", disconnect),
+                Action::disconnect("bar"),
+                Action::send("bar", join.clone()),
+            ]
+        );
+
+        // Without coalescing, the queued actions are drained as they are.
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.join("bar");
+        node.join("bar");
+        let mut actions = Vec::new();
+        node.drain_actions(&mut actions);
+        assert_eq!(actions.len(), 2);
+        assert!(node.poll_action().is_none());
+
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.enable_action_coalescing();
+        node.enable_send_batching();
+        node.handle_protocol_message(ProtocolMessage::join(&"qux", None, false));
+        node.join("bar");
+        node.join("bar");
+        node.join("baz");
+        node.disconnect(&"qux", true);
+
+        let mut actions = Vec::new();
+        node.drain_actions(&mut actions);
+        let sends = actions
+            .iter()
+            .filter(|a| matches!(a, Action::Send { .. } | Action::SendBatch { .. }))
+            .count();
+        assert_eq!(sends, 3); // `JOIN_ACCEPT` to "qux" and `JOIN` to "bar" and "baz"
+        assert_eq!(node.metrics().sent(message::MessageKind::Join), 2);
+        assert!(actions.contains(&Action::send("bar", join)));
+    }
+
+    #[test]
+    fn cluster_report_works() {
+        use crate::cluster::{ClusterReport, ClusterThresholds};
+
+        let mut nodes = vec![
+            Node::new("foo", rand::thread_rng()),
+            Node::new("bar", rand::thread_rng()),
+            Node::new("baz", rand::thread_rng()),
+        ];
+        for node in &mut nodes[1..] {
+            node.join("foo");
+        }
+        execute_actions(&mut nodes);
+
+        let snapshots = nodes.iter().map(|n| n.snapshot()).collect::<Vec<_>>();
+        let report = ClusterReport::new(&snapshots, Some(&"foo"));
+        report
+            .check(&ClusterThresholds::default())
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        // "baz" leaves without notifying others
+        nodes[2].disconnect(&"foo", false);
+        nodes[2].disconnect(&"bar", false);
+        let snapshots = nodes.iter().map(|n| n.snapshot()).collect::<Vec<_>>();
+        let report = ClusterReport::new(&snapshots, Some(&"foo"));
+        assert_eq!(
+            to_set(report.asymmetric_edges.clone()),
+            to_set(vec![("foo", "baz"), ("bar", "baz")])
+        );
+        assert_eq!(report.isolated_nodes, ["baz"]);
+        assert_eq!(report.components.len(), 1);
+        assert_eq!(
+            to_set(report.components[0].clone()),
+            to_set(vec!["foo", "bar", "baz"])
+        );
+        assert!(report.unreachable_nodes.is_empty());
+        assert!(report.check(&ClusterThresholds::default()).is_err());
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn prometheus_text_works() {
+        let mut node = Node::new("foo", rand::thread_rng());
+        node.handle_protocol_message(ProtocolMessage::join(&"bar", None, false));
+        let labels = prometheus::Labels::new(&[("overlay", "a\"b")]).unwrap();
+        let text = node.metrics().to_prometheus_text(&labels);
+        assert!(text.contains("# TYPE hyparview_received_messages_total counter\n"));
+        assert!(text
+            .contains("hyparview_received_messages_total{overlay=\"a\\\"b\",kind=\"join\"} 1\n"));
+        assert!(text.contains("hyparview_active_view_size{overlay=\"a\\\"b\"} 1\n"));
+
+        // Invalid or reserved label names are rejected
+        use prometheus::{LabelError, Labels};
+        assert_eq!(
+            Labels::new(&[("over-lay", "a")]),
+            Err(LabelError::InvalidName("over-lay".to_owned()))
+        );
+        assert_eq!(
+            Labels::new(&[("0overlay", "a")]),
+            Err(LabelError::InvalidName("0overlay".to_owned()))
+        );
+        assert_eq!(
+            Labels::new(&[("kind", "a")]),
+            Err(LabelError::ReservedName("kind".to_owned()))
+        );
+        assert_eq!(
+            Labels::new(&[("a", "1"), ("a", "2")]),
+            Err(LabelError::DuplicateName("a".to_owned()))
+        );
+        let text = node.metrics().to_prometheus_text(&Labels::default());
+        assert!(text.contains("hyparview_active_view_size 1\n"));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn config_works() {
+        use crate::config::{Config, ConfigError};
+
+        let text = r#"
+[service.hyparview]
+seeds = ["foo", "bar"]
+
+[service.hyparview.options]
+max_active_view_size = 5
+tombstone_lifetime = 30
+
+[service.hyparview.options.failure_backoff]
+max_failures = 3
+max_backoff = 120
+
+[service.hyparview.intervals]
+probe_passive_view = 0.5
+"#;
+        let config = Config::<String>::from_toml_str(text, Some("service.hyparview")).unwrap();
+        assert_eq!(config.seeds, ["foo", "bar"]);
+        assert_eq!(config.options.max_active_view_size, 5);
+        assert_eq!(
+            config.options.max_passive_view_size,
+            NodeOptions::DEFAULT_MAX_PASSIVE_VIEW_SIZE
+        );
+        assert_eq!(
+            config.options.tombstone_lifetime,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(config.options.failure_backoff.max_failures, 3);
+        assert_eq!(
+            config.options.failure_backoff.max_backoff,
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            config.intervals.probe_passive_view,
+            Some(Duration::from_millis(500))
+        );
+
+        // Validation errors point to the offending keys
+        let text = "[hyparview.options]\nactive_random_walk_len = 1";
+        let e = Config::<String>::from_toml_str(text, Some("hyparview")).unwrap_err();
+        assert_eq!(e.key(), Some("hyparview.options.passive_random_walk_len"));
+
+        let text = "[intervals]\nfill_active_view = 0";
+        let e = Config::<String>::from_toml_str(text, None).unwrap_err();
+        assert_eq!(e.key(), Some("intervals.fill_active_view"));
+
+        // Out-of-range durations are rejected without panicking
+        let text = "[intervals]\nfill_active_view = 1e30";
+        let e = Config::<String>::from_toml_str(text, None).unwrap_err();
+        assert!(matches!(e, ConfigError::Parse(_)));
+        assert!(e.to_string().contains("intervals.fill_active_view"));
+
+        // Unknown keys are rejected
+        let text = "[options]\nmax_active_view = 1";
+        let e = Config::<String>::from_toml_str(text, None).unwrap_err();
+        assert!(matches!(e, ConfigError::Parse(_)));
+        assert!(e.to_string().contains("max_active_view"));
+
+        let e = Config::<String>::from_toml_str(text, Some("hyparview")).unwrap_err();
+        assert!(matches!(e, ConfigError::MissingSection(_)));
+    }
+
+    #[cfg(feature = "replay")]
+    #[test]
+    fn replay_works() {
+        use crate::replay::{Input, Recorder, Trace, TraceEntry, TraceHeader};
+
+        let header = TraceHeader {
+            node_id: "foo".to_owned(),
+            seed: 123,
+            options: NodeOptions::default(),
+        };
+        let mut recorder = Recorder::new(header, Vec::new()).unwrap();
+        for n in &["bar", "baz", "qux"] {
+            let message = ProtocolMessage::join(&n.to_string(), None, false);
+            recorder
+                .input(Input::HandleProtocolMessage(message))
+                .unwrap();
+        }
+        recorder.input(Input::ShufflePassiveView).unwrap();
+        let options = NodeOptions {
+            max_active_view_size: 2,
+            ..Default::default()
+        };
+        recorder.input(Input::ApplyOptions(options)).unwrap();
+        while recorder.poll_action().unwrap().is_some() {}
+
+        let (_, file) = recorder.into_inner();
+        let trace = Trace::<String>::read_from(&file[..]).unwrap();
+        let replayed = replay::replay(&trace, |_| {});
+        assert_eq!(trace.diff(&replayed), None);
+
+        // Reports the first divergence
+        let mut modified = trace.clone();
+        modified
+            .entries
+            .insert(1, TraceEntry::Input(Input::FillActiveView));
+        let replayed = replay::replay(&modified, |_| {});
+        let divergence = assert_some!(trace.diff(&replayed));
+        assert_eq!(divergence.index, 1);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_works() {
+        use std::fmt;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Metadata, Subscriber};
+
+        #[derive(Default, Clone)]
+        struct FieldRecorder(Arc<Mutex<Vec<String>>>);
+        impl FieldRecorder {
+            fn contains(&self, field: &str) -> bool {
+                self.0.lock().unwrap().iter().any(|f| f == field)
+            }
+        }
+        impl Visit for FieldRecorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                let field = format!("{}={:?}", field.name(), value);
+                self.0.lock().unwrap().push(field);
+            }
+        }
+        impl Subscriber for FieldRecorder {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes) -> Id {
+                span.record(&mut self.clone());
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &tracing::Event) {
+                event.record(&mut self.clone());
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        // Node IDs are not required to implement `Debug`
+        #[derive(Clone, PartialEq, Eq, Hash)]
+        struct OpaqueId(u32);
+
+        let recorder = FieldRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut node = Node::new(OpaqueId(0), rand::thread_rng());
+            node.handle_protocol_message(ProtocolMessage::join(&OpaqueId(1), None, false));
+        });
+        assert!(recorder.contains("sender=_"));
+        assert!(recorder.contains("message=accepted the join"));
+
+        let recorder = FieldRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut node = Node::new(0, rand::thread_rng());
+            node.enable_id_tracing();
+            node.handle_protocol_message(ProtocolMessage::join(&1, None, false));
+        });
+        assert!(recorder.contains("sender=1"));
+    }
+
+    #[test]
+    fn model_check_three_nodes() {
+        let options = NodeOptions {
+            max_active_view_size: 1,
+            max_passive_view_size: 1,
+            ..Default::default()
+        };
+        let mut model = model_check::Model::new(3, options);
+        model.max_drops = 1;
+        let stats = model.check().unwrap_or_else(|e| panic!("{}", e));
+        assert!(stats.quiescent_states > 0);
+        assert!(!stats.state_limit_reached);
+    }
+
+    #[test]
+    fn model_check_four_nodes() {
+        let options = NodeOptions {
+            max_active_view_size: 1,
+            max_passive_view_size: 1,
+            shuffle_active_view_size: 1,
+            shuffle_passive_view_size: 1,
+            active_random_walk_len: 2,
+            passive_random_walk_len: 1,
+            ..Default::default()
+        };
+        let model = model_check::Model::new(4, options);
+        let stats = model.check().unwrap_or_else(|e| panic!("{}", e));
+        assert!(stats.quiescent_states > 0);
+        assert_eq!(stats.truncated_paths, 0);
+        assert!(!stats.state_limit_reached);
+    }
+
+    // This takes more than ten seconds in debug builds.
+    #[test]
+    #[ignore]
+    fn model_check_four_nodes_exhaustive() {
+        let options = NodeOptions {
+            max_active_view_size: 2,
+            max_passive_view_size: 2,
+            active_random_walk_len: 2,
+            passive_random_walk_len: 1,
+            ..Default::default()
+        };
+        let mut model = model_check::Model::new(4, options);
+        model.max_depth = 16;
+        let stats = model.check().unwrap_or_else(|e| panic!("{}", e));
+        assert!(stats.quiescent_states > 0);
+        assert!(!stats.state_limit_reached);
+    }
+
+    fn deliver_messages(nodes: &mut [Node<&'static str, ThreadRng>]) {
+        let mut did_something = true;
+        while did_something {
+            did_something = false;
+            for i in 0..nodes.len() {
+                while let Some(action) = nodes[i].poll_action() {
+                    if let Action::Send {
+                        destination,
+                        message,
+                    } = action
+                    {
+                        did_something = true;
+                        if let Some(dest) = nodes.iter_mut().find(|n| *n.id() == destination) {
+                            dest.handle_protocol_message(message);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn execute_actions(nodes: &mut [Node<&'static str, ThreadRng>]) {
         let mut did_something = true;
         while did_something {
@@ -185,6 +1654,16 @@ mod tests {
                                 dest.handle_protocol_message(message);
                             }
                         }
+                        Action::SendBatch {
+                            destination,
+                            messages,
+                        } => {
+                            if let Some(dest) = nodes.iter_mut().find(|n| *n.id() == destination) {
+                                for message in messages {
+                                    dest.handle_protocol_message(message);
+                                }
+                            }
+                        }
                         Action::Disconnect { .. } => {}
                         Action::Notify { .. } => {}
                     }
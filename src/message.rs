@@ -1,14 +1,22 @@
 //! Messages used for inter-node communication.
-use crate::TimeToLive;
+use crate::{TimeToLive, ZoneId};
+use std::time::Duration;
 
 /// Messages used for inter-node communication.
 ///
 /// [HyParView]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProtocolMessage<T> {
     /// `JOIN` message.
     Join(JoinMessage<T>),
 
+    /// `JOIN_ACCEPT` message.
+    JoinAccept(JoinAcceptMessage<T>),
+
+    /// `JOIN_REDIRECT` message.
+    JoinRedirect(JoinRedirectMessage<T>),
+
     /// `FORWARD_JOIN` message.
     ForwardJoin(ForwardJoinMessage<T>),
 
@@ -23,70 +31,472 @@ pub enum ProtocolMessage<T> {
 
     /// `DISCONNECT` messsage.
     Disconnect(DisconnectMessage<T>),
+
+    /// `OPTIMIZATION` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    Optimization(OptimizationMessage<T>),
+
+    /// `OPTIMIZATION_REPLY` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    OptimizationReply(OptimizationReplyMessage<T>),
+
+    /// `REPLACE` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    Replace(ReplaceMessage<T>),
+
+    /// `REPLACE_REPLY` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    ReplaceReply(ReplaceReplyMessage<T>),
+
+    /// `SWITCH` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    Switch(SwitchMessage<T>),
+
+    /// `SWITCH_REPLY` message of the [X-BOT] extension.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    SwitchReply(SwitchReplyMessage<T>),
+
+    /// `TRY_LATER` message.
+    ///
+    /// This is sent as the reply of a message that exceeded the rate limit of the receiver.
+    TryLater(TryLaterMessage<T>),
+
+    /// `PING` message.
+    ///
+    /// This is sent for checking the liveness of a passive view member.
+    Ping(PingMessage<T>),
+
+    /// `PONG` message.
+    ///
+    /// This is sent as the reply of a `PING` message.
+    Pong(PongMessage<T>),
 }
 impl<T> ProtocolMessage<T> {
     /// Returns the node ID of the sender of the message.
     pub fn sender(&self) -> &T {
         match self {
             ProtocolMessage::Join(m) => &m.sender,
+            ProtocolMessage::JoinAccept(m) => &m.sender,
+            ProtocolMessage::JoinRedirect(m) => &m.sender,
             ProtocolMessage::ForwardJoin(m) => &m.sender,
             ProtocolMessage::Neighbor(m) => &m.sender,
             ProtocolMessage::Shuffle(m) => &m.sender,
             ProtocolMessage::ShuffleReply(m) => &m.sender,
             ProtocolMessage::Disconnect(m) => &m.sender,
+            ProtocolMessage::Optimization(m) => &m.sender,
+            ProtocolMessage::OptimizationReply(m) => &m.sender,
+            ProtocolMessage::Replace(m) => &m.sender,
+            ProtocolMessage::ReplaceReply(m) => &m.sender,
+            ProtocolMessage::Switch(m) => &m.sender,
+            ProtocolMessage::SwitchReply(m) => &m.sender,
+            ProtocolMessage::TryLater(m) => &m.sender,
+            ProtocolMessage::Ping(m) => &m.sender,
+            ProtocolMessage::Pong(m) => &m.sender,
+        }
+    }
+
+    /// Returns the incarnation of the sender of the message.
+    pub fn sender_incarnation(&self) -> u64 {
+        match self {
+            ProtocolMessage::Join(m) => m.sender_incarnation,
+            ProtocolMessage::JoinAccept(m) => m.sender_incarnation,
+            ProtocolMessage::JoinRedirect(m) => m.sender_incarnation,
+            ProtocolMessage::ForwardJoin(m) => m.sender_incarnation,
+            ProtocolMessage::Neighbor(m) => m.sender_incarnation,
+            ProtocolMessage::Shuffle(m) => m.sender_incarnation,
+            ProtocolMessage::ShuffleReply(m) => m.sender_incarnation,
+            ProtocolMessage::Disconnect(m) => m.sender_incarnation,
+            ProtocolMessage::Optimization(m) => m.sender_incarnation,
+            ProtocolMessage::OptimizationReply(m) => m.sender_incarnation,
+            ProtocolMessage::Replace(m) => m.sender_incarnation,
+            ProtocolMessage::ReplaceReply(m) => m.sender_incarnation,
+            ProtocolMessage::Switch(m) => m.sender_incarnation,
+            ProtocolMessage::SwitchReply(m) => m.sender_incarnation,
+            ProtocolMessage::TryLater(m) => m.sender_incarnation,
+            ProtocolMessage::Ping(m) => m.sender_incarnation,
+            ProtocolMessage::Pong(m) => m.sender_incarnation,
+        }
+    }
+
+    pub(crate) fn set_sender_incarnation(&mut self, incarnation: u64) {
+        match self {
+            ProtocolMessage::Join(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::JoinAccept(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::JoinRedirect(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::ForwardJoin(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Neighbor(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Shuffle(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::ShuffleReply(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Disconnect(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Optimization(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::OptimizationReply(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Replace(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::ReplaceReply(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Switch(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::SwitchReply(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::TryLater(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Ping(m) => m.sender_incarnation = incarnation,
+            ProtocolMessage::Pong(m) => m.sender_incarnation = incarnation,
+        }
+    }
+
+    /// Returns the kind of the message.
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            ProtocolMessage::Join(_) => MessageKind::Join,
+            ProtocolMessage::JoinAccept(_) => MessageKind::JoinAccept,
+            ProtocolMessage::JoinRedirect(_) => MessageKind::JoinRedirect,
+            ProtocolMessage::ForwardJoin(_) => MessageKind::ForwardJoin,
+            ProtocolMessage::Neighbor(_) => MessageKind::Neighbor,
+            ProtocolMessage::Shuffle(_) => MessageKind::Shuffle,
+            ProtocolMessage::ShuffleReply(_) => MessageKind::ShuffleReply,
+            ProtocolMessage::Disconnect(_) => MessageKind::Disconnect,
+            ProtocolMessage::Optimization(_) => MessageKind::Optimization,
+            ProtocolMessage::OptimizationReply(_) => MessageKind::OptimizationReply,
+            ProtocolMessage::Replace(_) => MessageKind::Replace,
+            ProtocolMessage::ReplaceReply(_) => MessageKind::ReplaceReply,
+            ProtocolMessage::Switch(_) => MessageKind::Switch,
+            ProtocolMessage::SwitchReply(_) => MessageKind::SwitchReply,
+            ProtocolMessage::TryLater(_) => MessageKind::TryLater,
+            ProtocolMessage::Ping(_) => MessageKind::Ping,
+            ProtocolMessage::Pong(_) => MessageKind::Pong,
+        }
+    }
+}
+impl<T> ProtocolMessage<T> {
+    /// Converts the node IDs in the message by `f`.
+    pub(crate) fn map<U, F>(self, mut f: F) -> ProtocolMessage<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        let f = &mut f;
+        match self {
+            ProtocolMessage::Join(m) => ProtocolMessage::Join(JoinMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                zone: m.zone,
+                redirected: m.redirected,
+            }),
+            ProtocolMessage::JoinAccept(m) => ProtocolMessage::JoinAccept(JoinAcceptMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                zone: m.zone,
+                nodes: m.nodes.into_iter().map(&mut *f).collect(),
+                zones: m.zones,
+                incarnations: m.incarnations,
+            }),
+            ProtocolMessage::JoinRedirect(m) => {
+                ProtocolMessage::JoinRedirect(JoinRedirectMessage {
+                    sender: f(m.sender),
+                    sender_incarnation: m.sender_incarnation,
+                    contacts: m.contacts.into_iter().map(&mut *f).collect(),
+                })
+            }
+            ProtocolMessage::ForwardJoin(m) => ProtocolMessage::ForwardJoin(ForwardJoinMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                new_node: f(m.new_node),
+                new_node_zone: m.new_node_zone,
+                new_node_incarnation: m.new_node_incarnation,
+                ttl: m.ttl,
+            }),
+            ProtocolMessage::Neighbor(m) => ProtocolMessage::Neighbor(NeighborMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                zone: m.zone,
+                high_priority: m.high_priority,
+            }),
+            ProtocolMessage::Shuffle(m) => ProtocolMessage::Shuffle(ShuffleMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                origin: f(m.origin),
+                nodes: m.nodes.into_iter().map(&mut *f).collect(),
+                zones: m.zones,
+                incarnations: m.incarnations,
+                tombstones: m.tombstones.into_iter().map(|t| t.map(&mut *f)).collect(),
+                ttl: m.ttl,
+            }),
+            ProtocolMessage::ShuffleReply(m) => {
+                ProtocolMessage::ShuffleReply(ShuffleReplyMessage {
+                    sender: f(m.sender),
+                    sender_incarnation: m.sender_incarnation,
+                    nodes: m.nodes.into_iter().map(&mut *f).collect(),
+                    zones: m.zones,
+                    incarnations: m.incarnations,
+                    tombstones: m.tombstones.into_iter().map(|t| t.map(&mut *f)).collect(),
+                })
+            }
+            ProtocolMessage::Disconnect(m) => ProtocolMessage::Disconnect(DisconnectMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                alive: m.alive,
+            }),
+            ProtocolMessage::Optimization(m) => {
+                ProtocolMessage::Optimization(OptimizationMessage {
+                    sender: f(m.sender),
+                    sender_incarnation: m.sender_incarnation,
+                    old_node: f(m.old_node),
+                })
+            }
+            ProtocolMessage::OptimizationReply(m) => {
+                ProtocolMessage::OptimizationReply(OptimizationReplyMessage {
+                    sender: f(m.sender),
+                    sender_incarnation: m.sender_incarnation,
+                    old_node: f(m.old_node),
+                    accepted: m.accepted,
+                })
+            }
+            ProtocolMessage::Replace(m) => ProtocolMessage::Replace(ReplaceMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                origin: f(m.origin),
+                old_node: f(m.old_node),
+            }),
+            ProtocolMessage::ReplaceReply(m) => {
+                ProtocolMessage::ReplaceReply(ReplaceReplyMessage {
+                    sender: f(m.sender),
+                    sender_incarnation: m.sender_incarnation,
+                    origin: f(m.origin),
+                    old_node: f(m.old_node),
+                    accepted: m.accepted,
+                })
+            }
+            ProtocolMessage::Switch(m) => ProtocolMessage::Switch(SwitchMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                origin: f(m.origin),
+                candidate: f(m.candidate),
+            }),
+            ProtocolMessage::SwitchReply(m) => ProtocolMessage::SwitchReply(SwitchReplyMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                origin: f(m.origin),
+                candidate: f(m.candidate),
+                accepted: m.accepted,
+            }),
+            ProtocolMessage::TryLater(m) => ProtocolMessage::TryLater(TryLaterMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+                kind: m.kind,
+                retry_after: m.retry_after,
+            }),
+            ProtocolMessage::Ping(m) => ProtocolMessage::Ping(PingMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+            }),
+            ProtocolMessage::Pong(m) => ProtocolMessage::Pong(PongMessage {
+                sender: f(m.sender),
+                sender_incarnation: m.sender_incarnation,
+            }),
         }
     }
 }
 impl<T: Clone> ProtocolMessage<T> {
-    pub(crate) fn join(sender: &T) -> Self {
+    pub(crate) fn join(sender: &T, zone: Option<ZoneId>, redirected: bool) -> Self {
         ProtocolMessage::Join(JoinMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
+            zone,
+            redirected,
         })
     }
 
-    pub(crate) fn forward_join(sender: &T, new_node: T, ttl: TimeToLive) -> Self {
+    pub(crate) fn join_accept(
+        sender: &T,
+        zone: Option<ZoneId>,
+        nodes: Vec<T>,
+        zones: Vec<Option<ZoneId>>,
+        incarnations: Vec<u64>,
+    ) -> Self {
+        ProtocolMessage::JoinAccept(JoinAcceptMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            zone,
+            nodes,
+            zones,
+            incarnations,
+        })
+    }
+
+    pub(crate) fn join_redirect(sender: &T, contacts: Vec<T>) -> Self {
+        ProtocolMessage::JoinRedirect(JoinRedirectMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            contacts,
+        })
+    }
+
+    pub(crate) fn forward_join(
+        sender: &T,
+        new_node: T,
+        new_node_zone: Option<ZoneId>,
+        new_node_incarnation: u64,
+        ttl: TimeToLive,
+    ) -> Self {
         ProtocolMessage::ForwardJoin(ForwardJoinMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
             new_node,
+            new_node_zone,
+            new_node_incarnation,
             ttl,
         })
     }
 
-    pub(crate) fn neighbor(sender: &T, high_priority: bool) -> Self {
+    pub(crate) fn neighbor(sender: &T, zone: Option<ZoneId>, high_priority: bool) -> Self {
         ProtocolMessage::Neighbor(NeighborMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
+            zone,
             high_priority,
         })
     }
 
-    pub(crate) fn shuffle(sender: &T, origin: T, nodes: Vec<T>, ttl: TimeToLive) -> Self {
+    pub(crate) fn shuffle(
+        sender: &T,
+        origin: T,
+        nodes: Vec<T>,
+        zones: Vec<Option<ZoneId>>,
+        incarnations: Vec<u64>,
+        tombstones: Vec<Tombstone<T>>,
+        ttl: TimeToLive,
+    ) -> Self {
         ProtocolMessage::Shuffle(ShuffleMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
             origin,
             nodes,
+            zones,
+            incarnations,
+            tombstones,
             ttl,
         })
     }
 
-    pub(crate) fn shuffle_reply(sender: &T, nodes: Vec<T>) -> Self {
+    pub(crate) fn shuffle_reply(
+        sender: &T,
+        nodes: Vec<T>,
+        zones: Vec<Option<ZoneId>>,
+        incarnations: Vec<u64>,
+        tombstones: Vec<Tombstone<T>>,
+    ) -> Self {
         ProtocolMessage::ShuffleReply(ShuffleReplyMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
             nodes,
+            zones,
+            incarnations,
+            tombstones,
         })
     }
 
     pub(crate) fn disconnect(sender: &T, alive: bool) -> Self {
         ProtocolMessage::Disconnect(DisconnectMessage {
             sender: sender.clone(),
+            sender_incarnation: 0,
             alive,
         })
     }
+
+    pub(crate) fn optimization(sender: &T, old_node: T) -> Self {
+        ProtocolMessage::Optimization(OptimizationMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            old_node,
+        })
+    }
+
+    pub(crate) fn optimization_reply(sender: &T, old_node: T, accepted: bool) -> Self {
+        ProtocolMessage::OptimizationReply(OptimizationReplyMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            old_node,
+            accepted,
+        })
+    }
+
+    pub(crate) fn replace(sender: &T, origin: T, old_node: T) -> Self {
+        ProtocolMessage::Replace(ReplaceMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            origin,
+            old_node,
+        })
+    }
+
+    pub(crate) fn replace_reply(sender: &T, origin: T, old_node: T, accepted: bool) -> Self {
+        ProtocolMessage::ReplaceReply(ReplaceReplyMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            origin,
+            old_node,
+            accepted,
+        })
+    }
+
+    pub(crate) fn switch(sender: &T, origin: T, candidate: T) -> Self {
+        ProtocolMessage::Switch(SwitchMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            origin,
+            candidate,
+        })
+    }
+
+    pub(crate) fn switch_reply(sender: &T, origin: T, candidate: T, accepted: bool) -> Self {
+        ProtocolMessage::SwitchReply(SwitchReplyMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            origin,
+            candidate,
+            accepted,
+        })
+    }
+
+    pub(crate) fn try_later(sender: &T, kind: MessageKind, retry_after: Duration) -> Self {
+        ProtocolMessage::TryLater(TryLaterMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+            kind,
+            retry_after,
+        })
+    }
+
+    pub(crate) fn ping(sender: &T) -> Self {
+        ProtocolMessage::Ping(PingMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+        })
+    }
+
+    pub(crate) fn pong(sender: &T) -> Self {
+        ProtocolMessage::Pong(PongMessage {
+            sender: sender.clone(),
+            sender_incarnation: 0,
+        })
+    }
 }
 impl<T> From<JoinMessage<T>> for ProtocolMessage<T> {
     fn from(f: JoinMessage<T>) -> Self {
         ProtocolMessage::Join(f)
     }
 }
+impl<T> From<JoinAcceptMessage<T>> for ProtocolMessage<T> {
+    fn from(f: JoinAcceptMessage<T>) -> Self {
+        ProtocolMessage::JoinAccept(f)
+    }
+}
+impl<T> From<JoinRedirectMessage<T>> for ProtocolMessage<T> {
+    fn from(f: JoinRedirectMessage<T>) -> Self {
+        ProtocolMessage::JoinRedirect(f)
+    }
+}
 impl<T> From<ForwardJoinMessage<T>> for ProtocolMessage<T> {
     fn from(f: ForwardJoinMessage<T>) -> Self {
         ProtocolMessage::ForwardJoin(f)
@@ -112,17 +522,179 @@ impl<T> From<DisconnectMessage<T>> for ProtocolMessage<T> {
         ProtocolMessage::Disconnect(f)
     }
 }
+impl<T> From<OptimizationMessage<T>> for ProtocolMessage<T> {
+    fn from(f: OptimizationMessage<T>) -> Self {
+        ProtocolMessage::Optimization(f)
+    }
+}
+impl<T> From<OptimizationReplyMessage<T>> for ProtocolMessage<T> {
+    fn from(f: OptimizationReplyMessage<T>) -> Self {
+        ProtocolMessage::OptimizationReply(f)
+    }
+}
+impl<T> From<ReplaceMessage<T>> for ProtocolMessage<T> {
+    fn from(f: ReplaceMessage<T>) -> Self {
+        ProtocolMessage::Replace(f)
+    }
+}
+impl<T> From<ReplaceReplyMessage<T>> for ProtocolMessage<T> {
+    fn from(f: ReplaceReplyMessage<T>) -> Self {
+        ProtocolMessage::ReplaceReply(f)
+    }
+}
+impl<T> From<SwitchMessage<T>> for ProtocolMessage<T> {
+    fn from(f: SwitchMessage<T>) -> Self {
+        ProtocolMessage::Switch(f)
+    }
+}
+impl<T> From<SwitchReplyMessage<T>> for ProtocolMessage<T> {
+    fn from(f: SwitchReplyMessage<T>) -> Self {
+        ProtocolMessage::SwitchReply(f)
+    }
+}
+impl<T> From<TryLaterMessage<T>> for ProtocolMessage<T> {
+    fn from(f: TryLaterMessage<T>) -> Self {
+        ProtocolMessage::TryLater(f)
+    }
+}
+impl<T> From<PingMessage<T>> for ProtocolMessage<T> {
+    fn from(f: PingMessage<T>) -> Self {
+        ProtocolMessage::Ping(f)
+    }
+}
+impl<T> From<PongMessage<T>> for ProtocolMessage<T> {
+    fn from(f: PongMessage<T>) -> Self {
+        ProtocolMessage::Pong(f)
+    }
+}
+
+/// Kinds of [ProtocolMessage](./enum.ProtocolMessage.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageKind {
+    /// `JOIN` message.
+    Join,
+
+    /// `JOIN_ACCEPT` message.
+    JoinAccept,
+
+    /// `JOIN_REDIRECT` message.
+    JoinRedirect,
+
+    /// `FORWARD_JOIN` message.
+    ForwardJoin,
+
+    /// `NEIGHBOR` message.
+    Neighbor,
+
+    /// `SHUFFLE` message.
+    Shuffle,
+
+    /// `SHUFFLE_REPLY` message.
+    ShuffleReply,
+
+    /// `DISCONNECT` message.
+    Disconnect,
+
+    /// `OPTIMIZATION` message.
+    Optimization,
+
+    /// `OPTIMIZATION_REPLY` message.
+    OptimizationReply,
+
+    /// `REPLACE` message.
+    Replace,
+
+    /// `REPLACE_REPLY` message.
+    ReplaceReply,
+
+    /// `SWITCH` message.
+    Switch,
+
+    /// `SWITCH_REPLY` message.
+    SwitchReply,
+
+    /// `TRY_LATER` message.
+    TryLater,
+
+    /// `PING` message.
+    Ping,
+
+    /// `PONG` message.
+    Pong,
+}
 
 /// `JOIN` message.
 ///
 /// This is sent by new nodes for joining a HyParView cluster.
 /// The receiver is the contact node of the cluster.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JoinMessage<T> {
     /// The node ID of the message sender.
     ///
     /// It is also a new node that wishes to join the cluster.
     pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The zone of the sender.
+    pub zone: Option<ZoneId>,
+
+    /// Whether this message has been sent by following a `JOIN_REDIRECT` message.
+    ///
+    /// A redirected `JOIN` message is never redirected again.
+    pub redirected: bool,
+}
+
+/// `JOIN_ACCEPT` message.
+///
+/// This is sent by the contact node as the reply of a `JOIN` message.
+/// The receiver adds the sender to its active view and `nodes` to its passive view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JoinAcceptMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The zone of the sender.
+    pub zone: Option<ZoneId>,
+
+    /// The nodes sampled from the active and passive views of the sender.
+    pub nodes: Vec<T>,
+
+    /// The zones of `nodes`.
+    ///
+    /// The i-th element is the zone of the i-th node.
+    /// If this is shorter than `nodes`, the zones of the remaining nodes are regarded as unknown.
+    pub zones: Vec<Option<ZoneId>>,
+
+    /// The incarnations of `nodes`.
+    ///
+    /// The i-th element is the incarnation of the i-th node.
+    /// If this is shorter than `nodes`, the incarnations of the remaining nodes are regarded as unknown.
+    pub incarnations: Vec<u64>,
+}
+
+/// `JOIN_REDIRECT` message.
+///
+/// This is sent by an overloaded contact node as the reply of a `JOIN` message.
+/// The receiver will send a `JOIN` message to one of `contacts` instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JoinRedirectMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// Alternative contact nodes selected from the views of the sender.
+    pub contacts: Vec<T>,
 }
 
 /// `FORWARD_JOIN` message.
@@ -130,13 +702,23 @@ pub struct JoinMessage<T> {
 /// This is used for disseminating a `JOIN` request to the members of the cluster to
 /// which the contact node belongs.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForwardJoinMessage<T> {
     /// The node ID of the message sender.
     pub sender: T,
 
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
     /// The ID of the new joining node.
     pub new_node: T,
 
+    /// The zone of the new joining node.
+    pub new_node_zone: Option<ZoneId>,
+
+    /// The incarnation of the new joining node.
+    pub new_node_incarnation: u64,
+
     /// TTL of the message.
     pub ttl: TimeToLive,
 }
@@ -149,10 +731,17 @@ pub struct ForwardJoinMessage<T> {
 /// HyParView level connection has been established
 /// (in that case the value of `high_priority` always be set to `true`).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NeighborMessage<T> {
     /// The node ID of the message sender.
     pub sender: T,
 
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The zone of the sender.
+    pub zone: Option<ZoneId>,
+
     /// Whether the priority of the sender is high or low.
     pub high_priority: bool,
 }
@@ -161,40 +750,274 @@ pub struct NeighborMessage<T> {
 ///
 /// This and `SHUFFLE_REPLY` messages are used for shuffling passive views of two nodes.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShuffleMessage<T> {
     /// The node ID of the message sender.
     pub sender: T,
 
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
     /// The ID of the origin node that emitted the shuffle request.
     pub origin: T,
 
     /// The nodes selected by `origin` for shuffling.
     pub nodes: Vec<T>,
 
+    /// The zones of `nodes`.
+    ///
+    /// The i-th element is the zone of the i-th node.
+    /// If this is shorter than `nodes`, the zones of the remaining nodes are regarded as unknown.
+    pub zones: Vec<Option<ZoneId>>,
+
+    /// The incarnations of `nodes`.
+    ///
+    /// The i-th element is the incarnation of the i-th node.
+    /// If this is shorter than `nodes`, the incarnations of the remaining nodes are regarded as unknown.
+    pub incarnations: Vec<u64>,
+
+    /// The nodes regarded as dead by `origin`.
+    pub tombstones: Vec<Tombstone<T>>,
+
     /// TTL of the message.
     pub ttl: TimeToLive,
 }
 
 /// `SHUFFLE_REPLY` message.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShuffleReplyMessage<T> {
     /// The node ID of the message sender.
     pub sender: T,
 
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
     /// The nodes selected by `sender` as the reply of the associated `Shuffle` message.
     pub nodes: Vec<T>,
+
+    /// The zones of `nodes`.
+    ///
+    /// The i-th element is the zone of the i-th node.
+    /// If this is shorter than `nodes`, the zones of the remaining nodes are regarded as unknown.
+    pub zones: Vec<Option<ZoneId>>,
+
+    /// The incarnations of `nodes`.
+    ///
+    /// The i-th element is the incarnation of the i-th node.
+    /// If this is shorter than `nodes`, the incarnations of the remaining nodes are regarded as unknown.
+    pub incarnations: Vec<u64>,
+
+    /// The nodes regarded as dead by `sender`.
+    pub tombstones: Vec<Tombstone<T>>,
 }
 
 /// `DISCONNECT` message.
 ///
 /// This is sent by a node for removing the sender from the active view of the receiver.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisconnectMessage<T> {
     /// The node ID of the message sender.
     pub sender: T,
 
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
     /// Whether the sender is alive or not.
     ///
     /// If it is `false`, the receiver of the message will remove the sender from its passive view.
     pub alive: bool,
 }
+
+/// `OPTIMIZATION` message.
+///
+/// This is sent by a node (the origin) to a candidate node in its passive view
+/// for replacing the costly link to `old_node` with a cheaper link to the candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OptimizationMessage<T> {
+    /// The node ID of the message sender.
+    ///
+    /// It is also the origin of the optimization.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the active view member which the sender wants to replace.
+    pub old_node: T,
+}
+
+/// `OPTIMIZATION_REPLY` message.
+///
+/// This is sent by the candidate node to the origin node of an optimization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OptimizationReplyMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the active view member which the origin wants to replace.
+    pub old_node: T,
+
+    /// Whether the optimization has been accepted or not.
+    ///
+    /// If it is `true`, the sender has already added the origin to its active view.
+    pub accepted: bool,
+}
+
+/// `REPLACE` message.
+///
+/// This is sent by the candidate node of an optimization to one of its neighbors
+/// if the active view of the candidate is full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplaceMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the origin node of the optimization.
+    pub origin: T,
+
+    /// The ID of the active view member which the origin wants to replace.
+    pub old_node: T,
+}
+
+/// `REPLACE_REPLY` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplaceReplyMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the origin node of the optimization.
+    pub origin: T,
+
+    /// The ID of the active view member which the origin wants to replace.
+    pub old_node: T,
+
+    /// Whether the replacement has been accepted or not.
+    pub accepted: bool,
+}
+
+/// `SWITCH` message.
+///
+/// This is sent by the node that received a `REPLACE` message to `old_node`
+/// for asking it to switch the link to the origin with a link to the sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwitchMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the origin node of the optimization.
+    pub origin: T,
+
+    /// The ID of the candidate node of the optimization.
+    pub candidate: T,
+}
+
+/// `SWITCH_REPLY` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwitchReplyMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The ID of the origin node of the optimization.
+    pub origin: T,
+
+    /// The ID of the candidate node of the optimization.
+    pub candidate: T,
+
+    /// Whether the switch has been accepted or not.
+    pub accepted: bool,
+}
+
+/// `TRY_LATER` message.
+///
+/// This is sent by a node that discarded an incoming message due to rate limiting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TryLaterMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+
+    /// The kind of the discarded message.
+    pub kind: MessageKind,
+
+    /// The duration after which the receiver may retry.
+    pub retry_after: Duration,
+}
+
+/// `PING` message.
+///
+/// This is sent by a node for checking the liveness of a member of its passive view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PingMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+}
+
+/// `PONG` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PongMessage<T> {
+    /// The node ID of the message sender.
+    pub sender: T,
+
+    /// The incarnation of the sender.
+    pub sender_incarnation: u64,
+}
+
+/// A node regarded as dead, gossiped in `SHUFFLE` and `SHUFFLE_REPLY` messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tombstone<T> {
+    /// The ID of the dead node.
+    pub node: T,
+
+    /// The incarnation of the dead node.
+    ///
+    /// Newer incarnations of the node are not affected by the tombstone.
+    pub incarnation: u64,
+
+    /// The remaining lifetime of the tombstone.
+    pub lifetime: Duration,
+}
+impl<T> Tombstone<T> {
+    /// Converts the node ID in the tombstone by `f`.
+    pub(crate) fn map<U, F>(self, f: F) -> Tombstone<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Tombstone {
+            node: f(self.node),
+            incarnation: self.incarnation,
+            lifetime: self.lifetime,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::message::MessageKind;
+use std::collections::BTreeMap;
+
+/// Metrics of HyParView [Node](./struct.Node.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeMetrics {
+    /// Number of received messages per message kind.
+    pub received_messages: BTreeMap<MessageKind, u64>,
+
+    /// Number of sent messages per message kind.
+    pub sent_messages: BTreeMap<MessageKind, u64>,
+
+    /// Number of received messages that were discarded without being handled
+    /// (e.g., due to rate limiting or stale incarnations).
+    pub rejected_messages: u64,
+
+    /// Number of nodes evicted from the active view for making room for other nodes.
+    pub active_view_evictions: u64,
+
+    /// Number of nodes evicted from the passive view for making room for other nodes.
+    pub passive_view_evictions: u64,
+
+    /// Number of nodes moved from the passive view to the active view.
+    pub promotions: u64,
+
+    /// Current number of nodes in the active view.
+    pub active_view_size: usize,
+
+    /// Current number of nodes in the passive view.
+    pub passive_view_size: usize,
+}
+impl NodeMetrics {
+    /// Returns the number of received messages of the given kind.
+    pub fn received(&self, kind: MessageKind) -> u64 {
+        self.received_messages.get(&kind).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of sent messages of the given kind.
+    pub fn sent(&self, kind: MessageKind) -> u64 {
+        self.sent_messages.get(&kind).cloned().unwrap_or(0)
+    }
+
+    pub(crate) fn record_received(&mut self, kind: MessageKind) {
+        *self.received_messages.entry(kind).or_insert(0) += 1;
+    }
+
+    pub(crate) fn record_sent(&mut self, kind: MessageKind) {
+        *self.sent_messages.entry(kind).or_insert(0) += 1;
+    }
+}
@@ -0,0 +1,325 @@
+//! Exhaustive state space explorer for small HyParView clusters (test only).
+//!
+//! The explorer enumerates all orderings (and optionally drops) of in-flight messages
+//! in breadth-first order, so the first counterexample found is a shortest one.
+//! Like TCP connections, the messages between two nodes are delivered in FIFO order.
+//!
+//! Each state is reconstructed by re-executing its path from the initial state,
+//! because `Node` can't be cloned. The nodes use RNGs seeded by their IDs,
+//! so the re-execution is deterministic.
+//!
+//! Paths reaching the same state are explored only once.
+//! A state consists of the whole states of the nodes (including their RNGs)
+//! and the in-flight messages of each channel in order.
+use crate::message::ProtocolMessage;
+use crate::{Action, Node, NodeOptions};
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+type NodeId = u8;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Step {
+    Deliver(usize),
+    Drop(usize),
+}
+
+#[derive(Debug)]
+pub struct Counterexample {
+    pub invariant: String,
+    pub trace: Vec<String>,
+}
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "invariant {:?} violated:", self.invariant)?;
+        for (i, step) in self.trace.iter().enumerate() {
+            writeln!(f, "  #{}: {}", i, step)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub states: usize,
+    pub quiescent_states: usize,
+    pub truncated_paths: usize,
+
+    // Whether the exploration was stopped by `Model::max_states`.
+    pub state_limit_reached: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub node_count: u8,
+    pub options: NodeOptions,
+
+    // Maximum number of dropped messages in a path.
+    pub max_drops: usize,
+
+    // Maximum length of a path.
+    //
+    // HyParView may not converge under adversarial scheduling (e.g., endless evictions),
+    // so the exploration is bounded.
+    pub max_depth: usize,
+
+    pub max_states: usize,
+}
+impl Model {
+    pub fn new(node_count: u8, options: NodeOptions) -> Self {
+        Model {
+            node_count,
+            options,
+            max_drops: 0,
+            max_depth: 32,
+            max_states: 1_000_000,
+        }
+    }
+
+    pub fn check(&self) -> Result<Stats, Counterexample> {
+        let mut stats = Stats::default();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(Vec::new());
+        while let Some(path) = queue.pop_front() {
+            let mut world = self.execute(&path);
+            if !visited.insert(world.fingerprint()) {
+                continue;
+            }
+            if stats.states == self.max_states {
+                stats.state_limit_reached = true;
+                break;
+            }
+            stats.states += 1;
+
+            if let Err(invariant) = world.check_safety() {
+                return Err(self.counterexample(invariant, &path));
+            }
+            if world.network.is_empty() {
+                stats.quiescent_states += 1;
+                let mut world = world;
+                world.repair();
+                if let Err(invariant) = world.check_symmetry() {
+                    return Err(self.counterexample(invariant, &path));
+                }
+                continue;
+            }
+            if path.len() >= self.max_depth {
+                stats.truncated_paths += 1;
+                continue;
+            }
+            for i in world.deliverable_messages() {
+                let mut next = path.clone();
+                next.push(Step::Deliver(i));
+                queue.push_back(next);
+                if world.drops < self.max_drops {
+                    let mut next = path.clone();
+                    next.push(Step::Drop(i));
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn initial_world(&self) -> World {
+        let nodes = (0..self.node_count)
+            .map(|id| {
+                let rng = TracedRng::new(StdRng::seed_from_u64(u64::from(id)));
+                Node::with_options(id, rng, self.options.clone())
+            })
+            .collect();
+        let mut world = World {
+            nodes,
+            network: Vec::new(),
+            drops: 0,
+        };
+        for i in 1..world.nodes.len() {
+            world.nodes[i].join(0);
+            world.collect_messages(i);
+        }
+        world
+    }
+
+    fn execute(&self, path: &[Step]) -> World {
+        let mut world = self.initial_world();
+        for step in path {
+            world.step(*step);
+        }
+        world
+    }
+
+    fn counterexample(&self, invariant: String, path: &[Step]) -> Counterexample {
+        let mut world = self.initial_world();
+        let mut trace = Vec::new();
+        for step in path {
+            let (i, verb) = match *step {
+                Step::Deliver(i) => (i, "deliver"),
+                Step::Drop(i) => (i, "drop"),
+            };
+            let (from, to, message) = &world.network[i];
+            trace.push(format!("{} {} -> {}: {:?}", verb, from, to, message));
+            world.step(*step);
+        }
+        for node in &world.nodes {
+            trace.push(format!(
+                "node {}: active={:?}, passive={:?}",
+                node.id(),
+                node.active_view(),
+                node.passive_view()
+            ));
+        }
+        Counterexample { invariant, trace }
+    }
+}
+
+// RNG that remembers (the hash of) the history of the calls.
+//
+// The state of a seeded RNG is determined by the history, while `StdRng` doesn't expose its state.
+#[derive(Debug)]
+struct TracedRng {
+    inner: StdRng,
+    history: u64,
+}
+impl TracedRng {
+    fn new(inner: StdRng) -> Self {
+        TracedRng { inner, history: 0 }
+    }
+
+    fn record(&mut self, call: (u8, usize)) {
+        let mut hasher = DefaultHasher::new();
+        (self.history, call).hash(&mut hasher);
+        self.history = hasher.finish();
+    }
+}
+impl RngCore for TracedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.record((0, 0));
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.record((1, 0));
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.record((2, dest.len()));
+        self.inner.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.record((2, dest.len()));
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+struct World {
+    nodes: Vec<Node<NodeId, TracedRng>>,
+    network: Vec<(NodeId, NodeId, ProtocolMessage<NodeId>)>,
+    drops: usize,
+}
+impl World {
+    fn step(&mut self, step: Step) {
+        match step {
+            Step::Deliver(i) => {
+                let (_, to, message) = self.network.remove(i);
+                self.nodes[to as usize].handle_protocol_message(message);
+                self.collect_messages(to as usize);
+            }
+            Step::Drop(i) => {
+                self.network.remove(i);
+                self.drops += 1;
+            }
+        }
+    }
+
+    // Returns the indices of the oldest messages of the channels.
+    //
+    // The messages between two nodes are delivered in FIFO order like TCP connections.
+    fn deliverable_messages(&self) -> Vec<usize> {
+        (0..self.network.len())
+            .filter(|&i| {
+                let (from, to, _) = &self.network[i];
+                !self.network[..i].iter().any(|m| m.0 == *from && m.1 == *to)
+            })
+            .collect()
+    }
+
+    fn collect_messages(&mut self, i: usize) {
+        let from = *self.nodes[i].id();
+        while let Some(action) = self.nodes[i].poll_action() {
+            if let Action::Send {
+                destination,
+                message,
+            } = action
+            {
+                self.network.push((from, destination, message));
+            }
+        }
+    }
+
+    // Delivers all in-flight messages in FIFO order.
+    fn deliver_all(&mut self) {
+        while !self.network.is_empty() {
+            self.step(Step::Deliver(0));
+        }
+    }
+
+    // Runs the periodic maintenance for recovering from message drops.
+    fn repair(&mut self) {
+        for _ in 0..2 {
+            for i in 0..self.nodes.len() {
+                self.nodes[i].sync_active_view();
+                self.collect_messages(i);
+            }
+            self.deliver_all();
+        }
+    }
+
+    // Returns a 128-bit hash of the state.
+    //
+    // The order of the messages is significant only within each channel.
+    // Metrics are excluded because they don't affect the behavior of the nodes.
+    fn fingerprint(&mut self) -> (u64, u64) {
+        let mut channels = BTreeMap::new();
+        for (from, to, message) in &self.network {
+            let channel = channels.entry((from, to)).or_insert_with(Vec::new);
+            channel.push(format!("{:?}", message));
+        }
+        for node in &mut self.nodes {
+            node.reset_metrics();
+        }
+        let state = format!("{:?}/{:?}/{}", self.nodes, channels, self.drops);
+
+        let mut hashers = (DefaultHasher::new(), DefaultHasher::new());
+        state.hash(&mut hashers.0);
+        (1u8, state).hash(&mut hashers.1);
+        (hashers.0.finish(), hashers.1.finish())
+    }
+
+    fn check_safety(&self) -> Result<(), String> {
+        for node in &self.nodes {
+            if let Some(violation) = node.check_invariants().into_iter().next() {
+                return Err(format!("node {}: {}", node.id(), violation));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_symmetry(&self) -> Result<(), String> {
+        for node in &self.nodes {
+            for peer in node.active_view() {
+                let peer = &self.nodes[*peer as usize];
+                if !peer.active_view().contains(node.id()) {
+                    return Err("eventual active view symmetry".to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+}
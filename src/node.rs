@@ -1,12 +1,33 @@
+use crate::action::{batch_sends, remove_redundant_actions};
+use crate::backoff::FailureMemory;
+use crate::bucket::PassiveViewBuckets;
+use crate::cluster::NodeSnapshot;
 use crate::message::{
-    DisconnectMessage, ForwardJoinMessage, JoinMessage, NeighborMessage, ProtocolMessage,
-    ShuffleMessage, ShuffleReplyMessage,
+    DisconnectMessage, ForwardJoinMessage, JoinAcceptMessage, JoinMessage, JoinRedirectMessage,
+    MessageKind, NeighborMessage, OptimizationMessage, OptimizationReplyMessage, PingMessage,
+    PongMessage, ProtocolMessage, ReplaceMessage, ReplaceReplyMessage, ShuffleMessage,
+    ShuffleReplyMessage, SwitchMessage, SwitchReplyMessage, Tombstone, TryLaterMessage,
+};
+use crate::probe::Prober;
+use crate::rate_limit::{RateLimiter, TokenBucket};
+use crate::reputation::Reputation;
+use crate::tombstone::Tombstones;
+#[cfg(feature = "tracing")]
+use crate::trace::{IdFormatter, TracedId, TracedIds};
+use crate::view::{IndexedVec, View};
+use crate::xbot::Xbot;
+use crate::{
+    Action, FailureState, GroupKey, IdInterner, Ids, InvariantViolation, LinkCostOracle,
+    NodeMetrics, NodeOptions, NodeOptionsError, ProbeOptions, RateLimitPolicy, RateLimitStats,
+    ReputationEvent, ReputationWeighting, SharedId, TimeToLive, XbotOptions, ZoneId,
 };
-use crate::{Action, NodeOptions, TimeToLive};
 use rand::rngs::ThreadRng;
-use rand::seq::SliceRandom;
+use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
 use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::time::Duration;
 
 /// HyParView node.
 ///
@@ -21,18 +42,43 @@ use std::collections::VecDeque;
 /// For recovering the connectivity,
 /// an upper layer have to provide some kind of connectivity checking mechanism.
 /// And when the cluster division is detected, `Node::join` method should be called in some nodes.
+///
+/// # Note on the cost of node IDs
+///
+/// Node IDs are interned when they are received, and are held as [SharedId](./struct.SharedId.html)s internally.
+/// So, they are cloned only when the resulting actions are returned by `Node::poll_action` or `Node::drain_actions`.
 #[derive(Debug)]
 pub struct Node<T, R = ThreadRng> {
-    id: T,
-    actions: VecDeque<Action<T>>,
-    active_view: Vec<T>,
-    passive_view: Vec<T>,
+    id: SharedId<T>,
+    ids: IdInterner<T>,
+    actions: VecDeque<Action<SharedId<T>>>,
+    active_view: View<SharedId<T>>,
+    passive_view: View<SharedId<T>>,
     rng: R,
     options: NodeOptions,
+    xbot: Option<Xbot<T>>,
+    zone: Option<ZoneId>,
+    incarnation: u64,
+    peers: IndexedVec<SharedId<T>, PeerInfo<SharedId<T>>>,
+    passive_view_buckets: Option<PassiveViewBuckets<SharedId<T>>>,
+    prober: Option<Prober<SharedId<T>>>,
+    now: Duration,
+    rate_limiter: RateLimiter<SharedId<T>>,
+    tombstones: Tombstones<SharedId<T>>,
+    failures: FailureMemory<SharedId<T>>,
+    reputation: Option<Reputation<SharedId<T>>>,
+    metrics: NodeMetrics,
+    rejoin: Option<(SharedId<T>, Duration)>,
+    join_bucket: Option<TokenBucket>,
+    invariant_checks: bool,
+    action_coalescing: bool,
+    send_batching: bool,
+    #[cfg(feature = "tracing")]
+    id_formatter: Option<IdFormatter<T>>,
 }
 impl<T, R> Node<T, R>
 where
-    T: Clone + Eq,
+    T: Clone + Eq + Hash,
     R: Rng,
 {
     /// Makes a new `Node` instance with the default options.
@@ -42,13 +88,34 @@ where
 
     /// Makes a new `Node` instance with the given options.
     pub fn with_options(node_id: T, rng: R, options: NodeOptions) -> Self {
+        let mut ids = IdInterner::new();
         Node {
-            id: node_id,
+            id: ids.intern(node_id),
+            ids,
             actions: VecDeque::new(),
-            active_view: Vec::with_capacity(options.max_active_view_size as usize),
-            passive_view: Vec::with_capacity(options.max_passive_view_size as usize),
+            active_view: View::with_capacity(options.max_active_view_size as usize),
+            passive_view: View::with_capacity(options.max_passive_view_size as usize),
             rng,
             options,
+            xbot: None,
+            zone: None,
+            incarnation: 0,
+            peers: IndexedVec::with_key(0, |p| &p.id),
+            passive_view_buckets: None,
+            prober: None,
+            now: Duration::from_secs(0),
+            rate_limiter: RateLimiter::new(),
+            tombstones: Tombstones::new(),
+            failures: FailureMemory::new(),
+            reputation: None,
+            metrics: NodeMetrics::default(),
+            rejoin: None,
+            join_bucket: None,
+            invariant_checks: false,
+            action_coalescing: false,
+            send_batching: false,
+            #[cfg(feature = "tracing")]
+            id_formatter: None,
         }
     }
 
@@ -58,13 +125,13 @@ where
     }
 
     /// Returns a reference to the active view of the instance.
-    pub fn active_view(&self) -> &[T] {
-        &self.active_view
+    pub fn active_view(&self) -> Ids<'_, T> {
+        Ids::new(&self.active_view)
     }
 
     /// Returns a reference to the passive view of the instance.
-    pub fn passive_view(&self) -> &[T] {
-        &self.passive_view
+    pub fn passive_view(&self) -> Ids<'_, T> {
+        Ids::new(&self.passive_view)
     }
 
     /// Returns a reference to the options of the instance.
@@ -73,19 +140,299 @@ where
     }
 
     /// Returns a mutable reference to the options of the instance.
+    ///
+    /// Note that the views are not adjusted to the modified options immediately.
+    /// Use `Node::apply_options` method for resizing the views.
     pub fn options_mut(&mut self) -> &mut NodeOptions {
         &mut self.options
     }
 
+    /// Replaces the options of the instance with `options` and adjusts the views to them.
+    ///
+    /// If the views exceed the new maximum sizes or zones exceed their new quotas, excess nodes are evicted
+    /// (evicted active view members are notified by `DISCONNECT` messages).
+    /// If the active view is not full under the new options, promotions of as many nodes as
+    /// the free slots of the view are requested from the passive view.
+    ///
+    /// If `options` is invalid, this returns an error and the instance is not changed.
+    pub fn apply_options(&mut self, options: NodeOptions) -> Result<(), NodeOptionsError> {
+        options.validate()?;
+        self.options = options;
+
+        while let Some(zone) = self.find_zone_exceeding_quota() {
+            let same_zone_members = (0..self.active_view.len())
+                .filter(|&i| self.zone_of(&self.active_view[i]) == Some(zone))
+                .collect::<Vec<_>>();
+            let i = self
+                .select_eviction_index(false, same_zone_members.into_iter())
+                .expect("never fails");
+            trace_event!(
+                node = ?self.traced(&self.active_view[i]),
+                ?zone,
+                "evicted from the active view: zone quota lowered"
+            );
+            self.remove_from_active_view_by_index(i);
+            self.metrics.active_view_evictions += 1;
+        }
+        while self.active_view.len() > self.options.max_active_view_size as usize {
+            let i = self
+                .select_eviction_index(false, 0..self.active_view.len())
+                .expect("never fails");
+            trace_event!(
+                node = ?self.traced(&self.active_view[i]),
+                "evicted from the active view: view shrunk"
+            );
+            self.remove_from_active_view_by_index(i);
+            self.metrics.active_view_evictions += 1;
+        }
+        while self.passive_view.len() > self.options.max_passive_view_size as usize {
+            self.make_room_in_passive_view(None);
+        }
+        self.tombstones
+            .truncate(self.options.max_tombstones as usize);
+        if let Some(reputation) = self.reputation.as_mut() {
+            reputation.truncate(self.options.max_reputation_scores as usize);
+        }
+        let free_slots = self.options.max_active_view_size as usize - self.active_view.len();
+        self.request_promotions(free_slots);
+        Ok(())
+    }
+
+    /// Returns the statistics of the rate limiting of incoming messages.
+    pub fn rate_limit_stats(&self) -> &RateLimitStats {
+        self.rate_limiter.stats()
+    }
+
+    /// Returns the metrics of the instance.
+    pub fn metrics(&self) -> NodeMetrics {
+        let mut metrics = self.metrics.clone();
+        metrics.active_view_size = self.active_view.len();
+        metrics.passive_view_size = self.passive_view.len();
+        metrics
+    }
+
+    /// Resets the counters of the metrics of the instance.
+    pub fn reset_metrics(&mut self) {
+        self.metrics = NodeMetrics::default();
+    }
+
+    /// Returns the zone of the instance.
+    pub fn zone(&self) -> Option<ZoneId> {
+        self.zone
+    }
+
+    /// Sets the zone of the instance.
+    ///
+    /// The zone is notified to other nodes via `JOIN`, `FORWARD_JOIN`, `NEIGHBOR` and `SHUFFLE` messages.
+    pub fn set_zone(&mut self, zone: Option<ZoneId>) {
+        self.zone = zone;
+    }
+
+    /// Returns the zone of `node` if it is known by the instance.
+    ///
+    /// The zones are only kept for the members of the active and passive views.
+    pub fn zone_of(&self, node: &T) -> Option<ZoneId> {
+        self.ids.get(node).and_then(|n| self.known_zone(n))
+    }
+
+    /// Returns the incarnation of the instance.
+    pub fn incarnation(&self) -> u64 {
+        self.incarnation
+    }
+
+    /// Sets the incarnation of the instance.
+    ///
+    /// The incarnation distinguishes the lives of nodes that have the same ID.
+    /// When a node restarts with the same ID, it should use a larger incarnation than the previous life
+    /// (e.g., a persisted counter or the startup time).
+    ///
+    /// Messages sent by older incarnations of a node are ignored by the receivers,
+    /// and the entries of older incarnations in the views are replaced by newer ones.
+    pub fn set_incarnation(&mut self, incarnation: u64) {
+        self.incarnation = incarnation;
+    }
+
+    /// Returns the incarnation of `node` if it is known by the instance.
+    ///
+    /// The incarnations are only kept for the members of the active and passive views.
+    pub fn incarnation_of(&self, node: &T) -> Option<u64> {
+        self.ids.get(node).and_then(|n| self.known_incarnation(n))
+    }
+
+    /// Returns the reputation score of `node` if reputation scoring is enabled and `node` is known.
+    ///
+    /// The scores are kept even after `node` leaves the views, up to `NodeOptions::max_reputation_scores`.
+    pub fn reputation_of(&self, node: &T) -> Option<i64> {
+        let reputation = self.reputation.as_ref()?;
+        let node = self.ids.get(node)?;
+        reputation
+            .score(node)
+            .or_else(|| self.peer(node).map(|_| 0))
+    }
+
+    /// Returns the failure state of `node` if it has been reported as unreachable recently.
+    ///
+    /// The state is reset when `node` is added to the active view.
+    pub fn failure_state_of(&self, node: &T) -> Option<&FailureState> {
+        self.failures.get(self.ids.get(node)?)
+    }
+
+    /// Enables the [X-BOT] extension that biases the active view toward low-cost links.
+    ///
+    /// After calling this method, `Node::optimize_active_view` should be invoked periodically.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    pub fn enable_xbot<O>(&mut self, oracle: O, options: XbotOptions)
+    where
+        O: LinkCostOracle<T> + Send + Sync + 'static,
+    {
+        self.xbot = Some(Xbot::new(Box::new(oracle), options));
+    }
+
+    /// Enables bucketing of the passive view for resisting eclipse attacks.
+    ///
+    /// The members of the passive view are grouped into buckets by `GroupKey::group_key`,
+    /// and each bucket can hold at most `max_bucket_size` nodes.
+    /// If a bucket overflows, a member of the bucket is evicted.
+    /// If the whole passive view overflows, a member of the largest bucket is evicted.
+    ///
+    /// Thus, an attacker controlling many IDs in the same group (e.g., subnet)
+    /// can't flood the passive view via shuffles.
+    ///
+    /// `max_bucket_size` must be greater than zero (`0` is regarded as `1`).
+    pub fn enable_passive_view_buckets(&mut self, max_bucket_size: u16)
+    where
+        T: GroupKey,
+    {
+        let mut buckets = PassiveViewBuckets::new(max_bucket_size);
+        for node in &self.passive_view {
+            buckets.insert(node);
+        }
+        self.passive_view_buckets = Some(buckets);
+    }
+
+    /// Enables the liveness probing of the passive view.
+    ///
+    /// After calling this method, `Node::probe_passive_view` should be invoked periodically.
+    /// Members of the passive view that do not reply to `PING` messages within
+    /// `ProbeOptions::timeout` are removed from the view,
+    /// and recently confirmed members are preferred when promoting a node to the active view.
+    ///
+    /// Note that the timeouts are detected based on the clock advanced by `Node::advance_clock` method.
+    pub fn enable_passive_view_probing(&mut self, options: ProbeOptions) {
+        self.prober = Some(Prober::new(options));
+    }
+
+    /// Enables reputation scoring of peers.
+    ///
+    /// The score of a peer is the sum of the weights of the `ReputationEvent`s observed for the peer.
+    /// Peers with negative scores are avoided when promoting nodes to the active view and
+    /// forwarding messages, and are preferentially evicted from the views.
+    ///
+    /// `ReputationWeights` can be used as the default weighting.
+    pub fn enable_reputation<W>(&mut self, weighting: W)
+    where
+        W: ReputationWeighting + Send + Sync + 'static,
+    {
+        self.reputation = Some(Reputation::new(Box::new(weighting)));
+    }
+
+    /// Enables the invariant checks after every `Node::handle_protocol_message` call.
+    ///
+    /// The checks are performed only if debug assertions are enabled (see `Node::check_invariants`).
+    pub fn enable_invariant_checks(&mut self) {
+        self.invariant_checks = true;
+    }
+
+    /// Enables recording of node IDs in the spans and events emitted by the `tracing` feature.
+    ///
+    /// Without this, node IDs are recorded as `_`.
+    /// If the `tracing` feature is disabled, this has no effect.
+    pub fn enable_id_tracing(&mut self)
+    where
+        T: fmt::Debug,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            self.id_formatter = Some(|id, f| fmt::Debug::fmt(id, f));
+        }
+    }
+
+    /// Enables the removal of redundant actions by `Node::drain_actions`.
+    ///
+    /// A single operation (e.g., handling a `JOIN` message) may queue several actions for the same node,
+    /// and some of them are made redundant by later ones.
+    /// If this is enabled, the following actions are dropped:
+    /// - `Send` actions identical to the previous `Send` action to the same node (e.g., duplicate `NEIGHBOR` messages)
+    /// - `NEIGHBOR` messages to nodes that are disconnected later in the same drain
+    /// - `Disconnect` actions for nodes that have already been disconnected in the same drain
+    ///
+    /// Note that `Node::poll_action` returns the queued actions as they are.
+    pub fn enable_action_coalescing(&mut self) {
+        self.action_coalescing = true;
+    }
+
+    /// Enables packing of the messages to the same destination into `Action::SendBatch` by `Node::drain_actions`.
+    ///
+    /// This is useful for transports that can send multiple messages in a single frame or packet.
+    /// Messages are never reordered with respect to `Disconnect` actions for their destinations.
+    ///
+    /// Note that `Node::poll_action` never returns `Action::SendBatch`.
+    pub fn enable_send_batching(&mut self) {
+        self.send_batching = true;
+    }
+
+    /// Records `event` observed for `node`, if reputation scoring is enabled.
+    ///
+    /// Events that can be observed by the instance itself are recorded automatically,
+    /// so this method is used for reporting events detected by an upper layer
+    /// (e.g., messages that could not be decoded).
+    pub fn record_reputation_event(&mut self, node: &T, event: ReputationEvent) {
+        if let Some(node) = self.ids.get(node).cloned() {
+            self.record_reputation(&node, event);
+        }
+    }
+
+    /// Advances the internal clock of the instance by `elapsed`.
+    ///
+    /// The clock is used by time dependent features such as rate limiting, tombstones and probing.
+    /// If those features are enabled, this method should be invoked periodically.
+    pub fn advance_clock(&mut self, elapsed: Duration) {
+        self.now = self.now.saturating_add(elapsed);
+        self.rate_limiter.prune(&self.options.rate_limits, self.now);
+        self.tombstones.prune(self.now);
+        self.failures.prune(&self.options.failure_backoff, self.now);
+        self.record_active_uptime(elapsed);
+        let timed_out = match self.prober.as_mut() {
+            None => Vec::new(),
+            Some(prober) => prober.take_timed_out(self.now),
+        };
+        for node in timed_out {
+            self.remove_from_passive_view(&node);
+        }
+        if self.rejoin.as_ref().is_some_and(|x| x.1 <= self.now) {
+            let (contact_node_id, _) = self.rejoin.take().expect("never fails");
+            let message = ProtocolMessage::join(&self.id, self.zone, false);
+            send(&mut self.actions, contact_node_id, message);
+        }
+        self.ids.purge();
+    }
+
     /// Starts joining the cluster to which `contact_node_id` belongs.
     ///
     /// This method may be called multiple times for recovering cluster connectivity
     /// if an upper layer detects the cluster is splitted to sub-clusters.
+    ///
+    /// If the contact node replies `TRY_LATER` message,
+    /// the `JOIN` message will be resent automatically after the requested duration has elapsed.
+    /// Similarly, if the contact node replies `JOIN_REDIRECT` message,
+    /// the `JOIN` message will be sent to one of the alternative contact nodes automatically.
     pub fn join(&mut self, contact_node_id: T) {
+        let contact_node_id = self.ids.intern(contact_node_id);
         send(
             &mut self.actions,
             contact_node_id,
-            ProtocolMessage::join(&self.id),
+            ProtocolMessage::join(&self.id, self.zone, false),
         );
     }
 
@@ -96,20 +443,147 @@ where
     /// If the active view is not full, a node randomly selected from the passive view
     /// will be promoted to the active view if possible.
     ///
+    /// If `alive` is `false` (e.g., a failure of `node` has been detected) and
+    /// `NodeOptions::tombstone_lifetime` is set, a tombstone of `node` is recorded.
+    ///
     /// This is equivalent to the following code:
     /// ```norun
     /// let message = ProtocolMessage::Disconnect(DisconnectMessage{sender: node.clone(), alive});
     /// self.handle_protocol_message(message);
     /// ```
     pub fn disconnect(&mut self, node: &T, alive: bool) {
-        self.handle_protocol_message(ProtocolMessage::disconnect(node, alive));
+        let node = self.intern(node);
+        let mut message = ProtocolMessage::disconnect(&node, alive);
+        message.set_sender_incarnation(self.known_incarnation(&node).unwrap_or(0));
+        self.handle_interned_message(message);
+    }
+
+    /// Reports that the instance failed to connect to `node`
+    /// (e.g., the transport could not deliver a `NEIGHBOR` message sent for promoting `node`).
+    ///
+    /// `node` is not promoted to the active view until its backoff duration elapses.
+    /// The duration grows exponentially with consecutive failures,
+    /// and `node` is removed from the passive view after `BackoffOptions::max_failures` failures.
+    ///
+    /// Then, another node in the passive view will be promoted to the active view if possible.
+    pub fn report_unreachable(&mut self, node: &T) {
+        let node = self.intern(node);
+        let options = &self.options.failure_backoff;
+        let state = self
+            .failures
+            .record_failure(node.clone(), options, self.now);
+        let is_dropped =
+            options.max_failures != 0 && state.consecutive_failures >= options.max_failures;
+        self.record_reputation(&node, ReputationEvent::FailedSend);
+        if is_dropped {
+            self.remove_from_passive_view(&node);
+        }
+        self.fill_active_view();
     }
 
     /// Handles the given incoming message.
+    ///
+    /// `JOIN`, `FORWARD_JOIN` and `SHUFFLE` messages exceeding `NodeOptions::rate_limits`
+    /// are discarded (and may be answered with `TRY_LATER` messages).
+    ///
+    /// Messages sent by older incarnations of the sender are discarded.
+    /// If the sender has a newer incarnation than the known one,
+    /// it is regarded as restarted and removed from the active view before handling the message.
+    ///
+    /// If `Node::enable_invariant_checks` has been called and debug assertions are enabled,
+    /// this method panics when the state of the node violates its invariants after handling the message.
     pub fn handle_protocol_message(&mut self, message: ProtocolMessage<T>) {
+        let ids = &mut self.ids;
+        let message = message.map(|id| ids.intern(id));
+        self.handle_interned_message(message);
+    }
+
+    fn handle_interned_message(&mut self, message: ProtocolMessage<SharedId<T>>) {
+        self.handle_message(message);
+        self.ids.purge_if_grown();
+        if cfg!(debug_assertions) && self.invariant_checks {
+            let violations = self.check_invariants();
+            assert!(
+                violations.is_empty(),
+                "{} invariant(s) of the node are violated",
+                violations.len()
+            );
+        }
+    }
+
+    /// Checks the invariants of the state of the instance.
+    ///
+    /// The active and passive views must be disjoint, must contain neither duplicates nor the ID of the instance,
+    /// and must not exceed `NodeOptions::max_active_view_size` and `NodeOptions::max_passive_view_size` respectively.
+    ///
+    /// Returns the list of the detected violations (it is empty if the state is sane).
+    pub fn check_invariants(&self) -> Vec<InvariantViolation<T>> {
+        let mut violations = Vec::new();
+        if self.active_view.contains(&self.id) {
+            violations.push(InvariantViolation::SelfInActiveView);
+        }
+        if self.passive_view.contains(&self.id) {
+            violations.push(InvariantViolation::SelfInPassiveView);
+        }
+        for (i, n) in self.active_view.iter().enumerate() {
+            if self.active_view[..i].contains(n) {
+                violations.push(InvariantViolation::DuplicateInActiveView((**n).clone()));
+            }
+        }
+        for (i, n) in self.passive_view.iter().enumerate() {
+            if self.passive_view[..i].contains(n) {
+                violations.push(InvariantViolation::DuplicateInPassiveView((**n).clone()));
+            }
+        }
+        for n in &self.active_view {
+            if self.passive_view.contains(n) {
+                violations.push(InvariantViolation::InBothViews((**n).clone()));
+            }
+        }
+        let max_size = self.options.max_active_view_size as usize;
+        if self.active_view.len() > max_size {
+            violations.push(InvariantViolation::ActiveViewOverflow {
+                size: self.active_view.len(),
+                max_size,
+            });
+        }
+        let max_size = self.options.max_passive_view_size as usize;
+        if self.passive_view.len() > max_size {
+            violations.push(InvariantViolation::PassiveViewOverflow {
+                size: self.passive_view.len(),
+                max_size,
+            });
+        }
+        violations
+    }
+
+    /// Returns a snapshot of the views of the instance.
+    ///
+    /// Snapshots of multiple nodes can be analyzed by `cluster::ClusterReport`.
+    pub fn snapshot(&self) -> NodeSnapshot<T> {
+        NodeSnapshot {
+            id: (*self.id).clone(),
+            active_view: self.active_view().to_vec(),
+            passive_view: self.passive_view().to_vec(),
+        }
+    }
+
+    fn handle_message(&mut self, message: ProtocolMessage<SharedId<T>>) {
         let sender = message.sender().clone();
+        self.metrics.record_received(message.kind());
+        if !self.check_incarnation(&sender, message.sender_incarnation())
+            || !self.check_rate_limit(&sender, message.kind())
+        {
+            self.metrics.rejected_messages += 1;
+            return;
+        }
         match message {
             ProtocolMessage::Join(m) => self.handle_join(m),
+            ProtocolMessage::JoinAccept(m) => self.handle_join_accept(m),
+            ProtocolMessage::JoinRedirect(m) => {
+                self.handle_join_redirect(m);
+                return;
+            }
             ProtocolMessage::ForwardJoin(m) => self.handle_forward_join(m),
             ProtocolMessage::Neighbor(m) => self.handle_neighbor(m),
             ProtocolMessage::Shuffle(m) => self.handle_shuffle(m),
@@ -118,6 +592,42 @@ where
                 self.handle_disconnect(m);
                 return;
             }
+            ProtocolMessage::Optimization(m) => {
+                self.handle_optimization(m);
+                return;
+            }
+            ProtocolMessage::OptimizationReply(m) => {
+                self.handle_optimization_reply(m);
+                return;
+            }
+            ProtocolMessage::Replace(m) => {
+                self.handle_replace(m);
+                return;
+            }
+            ProtocolMessage::ReplaceReply(m) => {
+                self.handle_replace_reply(m);
+                return;
+            }
+            ProtocolMessage::Switch(m) => {
+                self.handle_switch(m);
+                return;
+            }
+            ProtocolMessage::SwitchReply(m) => {
+                self.handle_switch_reply(m);
+                return;
+            }
+            ProtocolMessage::TryLater(m) => {
+                self.handle_try_later(m);
+                return;
+            }
+            ProtocolMessage::Ping(m) => {
+                self.handle_ping(m);
+                return;
+            }
+            ProtocolMessage::Pong(m) => {
+                self.handle_pong(m);
+                return;
+            }
         }
         self.disconnect_unless_active_view_node(sender);
     }
@@ -127,11 +637,11 @@ where
     /// This method should be invoked periodically to keep the passive view fresh.
     pub fn shuffle_passive_view(&mut self) {
         if let Some(node) = self.select_random_from_active_view() {
-            self.passive_view.shuffle(&mut self.rng);
-            self.active_view.shuffle(&mut self.rng);
-
             let pv_size = self.options.shuffle_passive_view_size as usize;
             let av_size = self.options.shuffle_active_view_size as usize;
+            self.passive_view.partial_shuffle(&mut self.rng, pv_size);
+            self.active_view.partial_shuffle(&mut self.rng, av_size);
+
             let shuffle_size = 1 + pv_size + av_size;
 
             let mut nodes = Vec::with_capacity(shuffle_size);
@@ -139,22 +649,60 @@ where
             nodes.extend(self.active_view.iter().take(av_size).cloned());
             nodes.push(self.id.clone());
 
+            let (zones, incarnations) = self.describe_nodes(&nodes);
+            let tombstones = self.select_tombstones_for_gossip();
             let ttl = TimeToLive::new(self.options.active_random_walk_len);
-            let message = ProtocolMessage::shuffle(&self.id, self.id.clone(), nodes, ttl);
+            let origin = self.id.clone();
+            let message = ProtocolMessage::shuffle(
+                &self.id,
+                origin,
+                nodes,
+                zones,
+                incarnations,
+                tombstones,
+                ttl,
+            );
             send(&mut self.actions, node, message);
         }
     }
 
+    /// Sends `PING` messages to some members of the passive view for checking their liveness.
+    ///
+    /// The members that have not been confirmed for the longest time are probed first.
+    ///
+    /// This method should be invoked periodically if the passive view probing is enabled.
+    /// Otherwise it does nothing.
+    pub fn probe_passive_view(&mut self) {
+        let prober = match self.prober.as_ref() {
+            None => return,
+            Some(prober) => prober,
+        };
+        let mut candidates = self
+            .passive_view
+            .iter()
+            .filter(|n| !prober.is_pending(n))
+            .cloned()
+            .collect::<Vec<_>>();
+        candidates.shuffle(&mut self.rng);
+        candidates.sort_by_key(|n| self.peer(n).and_then(|p| p.last_confirmed));
+        candidates.truncate(prober.options.probes_per_round as usize);
+
+        let prober = self.prober.as_mut().expect("never fails");
+        for node in candidates {
+            prober.start(node.clone(), self.now);
+            send(&mut self.actions, node, ProtocolMessage::ping(&self.id));
+        }
+    }
+
     /// Promotes a node from the passive view to the active view if the latter is not full.
     ///
+    /// Nodes belonging to the zones whose quotas have been reached are not promoted
+    /// unless the active view is empty.
+    ///
     /// This method should be invoked periodically to keep the active view full.
     pub fn fill_active_view(&mut self) {
         if !self.is_active_view_full() {
-            if let Some(node) = self.select_random_from_passive_view() {
-                let high_priority = self.active_view.is_empty();
-                let message = ProtocolMessage::neighbor(&self.id, high_priority);
-                send(&mut self.actions, node, message);
-            }
+            self.request_promotions(1);
         }
     }
 
@@ -163,18 +711,121 @@ where
     ///
     /// This method should be invoked periodically to keep the symmetry property of the active view.
     pub fn sync_active_view(&mut self) {
-        for node in self.active_view.clone() {
-            let message = ProtocolMessage::neighbor(&self.id, false);
+        for node in self.active_view.iter().cloned() {
+            let message = ProtocolMessage::neighbor(&self.id, self.zone, false);
             send(&mut self.actions, node, message);
         }
     }
 
+    /// Tries to replace a costly member of the active view with a cheaper node in the passive view.
+    ///
+    /// This method should be invoked periodically if the [X-BOT] extension is enabled.
+    /// Otherwise it does nothing.
+    ///
+    /// Optimization is only attempted while the active view is full,
+    /// and at least `XbotOptions::unbiased_neighbors` members are never replaced.
+    ///
+    /// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    pub fn optimize_active_view(&mut self) {
+        if !self.is_active_view_full() {
+            return;
+        }
+        let xbot = match self.xbot.as_mut() {
+            None => return,
+            Some(xbot) => xbot,
+        };
+
+        let active_view = &self.active_view;
+        xbot.optimized.retain(|n| active_view.contains(n));
+        xbot.pending.retain(|x| active_view.contains(&x.1));
+        let unbiased = self.active_view.len() - xbot.optimized.len();
+
+        let mut old = None;
+        for n in &self.active_view {
+            if unbiased <= xbot.options.unbiased_neighbors as usize && !xbot.optimized.contains(n) {
+                continue;
+            }
+            let cost = xbot.oracle.link_cost(n);
+            if old.as_ref().is_none_or(|&(_, c)| c < cost) {
+                old = Some((n.clone(), cost));
+            }
+        }
+        let (old, old_cost) = match old {
+            None => return,
+            Some(x) => x,
+        };
+
+        let scan_len = xbot.options.passive_scan_len as usize;
+        self.passive_view.partial_shuffle(&mut self.rng, scan_len);
+        let mut candidate = None;
+        for n in self.passive_view.iter().take(scan_len) {
+            let cost = xbot.oracle.link_cost(n);
+            if cost < candidate.as_ref().map_or(old_cost, |&(_, c)| c) {
+                candidate = Some((n.clone(), cost));
+            }
+        }
+        if let Some((candidate, _)) = candidate {
+            xbot.start_optimization(candidate.clone(), old.clone());
+            let message = ProtocolMessage::optimization(&self.id, old);
+            send(&mut self.actions, candidate, message);
+        }
+    }
+
     /// Polls the next action that the node wants to execute.
     ///
     /// For running the HyParView node correctly,
     /// this method must be called periodically and the resulting action must be executed by the caller.
     pub fn poll_action(&mut self) -> Option<Action<T>> {
-        self.actions.pop_front()
+        let mut action = self.actions.pop_front()?;
+        self.prepare_action(&mut action);
+        Some(action.map(SharedId::into_inner))
+    }
+
+    /// Moves all the queued actions to the end of `actions`.
+    ///
+    /// This is equivalent to calling `Node::poll_action` until it returns `None`
+    /// except that the actions are coalesced or batched if
+    /// `Node::enable_action_coalescing` or `Node::enable_send_batching` has been called.
+    pub fn drain_actions(&mut self, actions: &mut Vec<Action<T>>) {
+        let mut drained = self.actions.drain(..).collect::<Vec<_>>();
+        if self.action_coalescing {
+            remove_redundant_actions(&mut drained);
+        }
+        if self.send_batching {
+            drained = batch_sends(drained);
+        }
+        for action in &mut drained {
+            self.prepare_action(action);
+        }
+        actions.extend(drained.into_iter().map(|a| a.map(SharedId::into_inner)));
+    }
+
+    #[cfg(feature = "tracing")]
+    fn traced<'a>(&self, id: &'a SharedId<T>) -> TracedId<'a, T> {
+        TracedId {
+            id: &**id,
+            formatter: self.id_formatter,
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn traced_all<'a>(&self, ids: &'a [SharedId<T>]) -> TracedIds<'a, T> {
+        TracedIds {
+            ids,
+            formatter: self.id_formatter,
+        }
+    }
+
+    fn prepare_action(&mut self, action: &mut Action<SharedId<T>>) {
+        let messages = match action {
+            Action::Send { message, .. } => std::slice::from_mut(message),
+            Action::SendBatch { messages, .. } => &mut messages[..],
+            _ => return,
+        };
+        for message in messages {
+            message.set_sender_incarnation(self.incarnation);
+            self.metrics.record_sent(message.kind());
+        }
     }
 
     fn is_active_view_full(&self) -> bool {
@@ -185,105 +836,672 @@ where
         self.passive_view.len() >= self.options.max_passive_view_size as usize
     }
 
-    fn handle_join(&mut self, m: JoinMessage<T>) {
+    fn check_incarnation(&mut self, sender: &SharedId<T>, incarnation: u64) -> bool {
+        let known = match self.peer(sender) {
+            None => return true,
+            Some(peer) => peer.incarnation,
+        };
+        if incarnation < known {
+            return false;
+        }
+        if known < incarnation {
+            let restarted = self.detach_from_active_view(sender);
+            self.update_peer(sender, None, Some(incarnation));
+            if restarted {
+                self.fill_active_view();
+            }
+        }
+        true
+    }
+
+    fn check_rate_limit(&mut self, sender: &SharedId<T>, kind: MessageKind) -> bool {
+        let limits = &self.options.rate_limits;
+        match self.rate_limiter.acquire(sender, kind, limits, self.now) {
+            Ok(()) => true,
+            Err(retry_after) => {
+                if self.options.rate_limits.policy == RateLimitPolicy::TryLater {
+                    let message = ProtocolMessage::try_later(&self.id, kind, retry_after);
+                    send(&mut self.actions, sender.clone(), message);
+                }
+                self.record_reputation(sender, ReputationEvent::RateLimited);
+                false
+            }
+        }
+    }
+
+    fn handle_join(&mut self, m: JoinMessage<SharedId<T>>) {
+        trace_span!(
+            "handle_join",
+            sender = ?self.traced(&m.sender),
+            redirected = m.redirected
+        );
+        if !m.redirected && self.is_overloaded_by_joins() {
+            let contacts = self.select_redirect_contacts(&m.sender);
+            if !contacts.is_empty() {
+                trace_event!(contacts = ?self.traced_all(&contacts), "redirected the join");
+                let message = ProtocolMessage::join_redirect(&self.id, contacts);
+                send(&mut self.actions, m.sender, message);
+                return;
+            }
+        }
+
         let new_node = m.sender;
-        self.add_to_active_view(new_node.clone(), true);
+        if new_node == self.id {
+            trace_event!("ignored the join from the instance itself");
+            return;
+        }
+        trace_event!("accepted the join");
+        let incarnation = m.sender_incarnation;
+        self.insert_into_active_view(new_node.clone(), m.zone, Some(incarnation));
+
+        let nodes = self.sample_views(&new_node);
+        let (zones, incarnations) = self.describe_nodes(&nodes);
+        let message = ProtocolMessage::join_accept(&self.id, self.zone, nodes, zones, incarnations);
+        send(&mut self.actions, new_node.clone(), message);
+
         let ttl = TimeToLive::new(self.options.active_random_walk_len);
         for n in self.active_view.iter().filter(|n| **n != new_node) {
-            let message = ProtocolMessage::forward_join(&self.id, new_node.clone(), ttl);
+            let message =
+                ProtocolMessage::forward_join(&self.id, new_node.clone(), m.zone, incarnation, ttl);
             send(&mut self.actions, n.clone(), message);
         }
     }
 
-    fn handle_forward_join(&mut self, m: ForwardJoinMessage<T>) {
+    fn handle_join_accept(&mut self, m: JoinAcceptMessage<SharedId<T>>) {
+        let incarnation = Some(m.sender_incarnation);
+        self.insert_into_active_view(m.sender, m.zone, incarnation);
+        self.add_shuffled_nodes_to_passive_view(m.nodes, m.zones, m.incarnations);
+    }
+
+    fn handle_join_redirect(&mut self, m: JoinRedirectMessage<SharedId<T>>) {
+        let mut contacts = m.contacts;
+        contacts.retain(|n| *n != self.id);
+        if contacts.is_empty() {
+            return;
+        }
+
+        let i = self.rng.gen_range(0, contacts.len());
+        let message = ProtocolMessage::join(&self.id, self.zone, true);
+        send(&mut self.actions, contacts.swap_remove(i), message);
+        for n in contacts {
+            self.add_to_passive_view(n, None, None);
+        }
+    }
+
+    fn handle_forward_join(&mut self, m: ForwardJoinMessage<SharedId<T>>) {
+        trace_span!(
+            "handle_forward_join",
+            sender = ?self.traced(&m.sender),
+            new_node = ?self.traced(&m.new_node),
+            ttl = m.ttl.as_u16()
+        );
+        let zone = m.new_node_zone;
+        let incarnation = Some(m.new_node_incarnation);
         if m.ttl.is_expired() || self.active_view.is_empty() {
-            self.add_to_active_view(m.new_node, true);
+            trace_event!("added the new node to the active view");
+            self.add_to_active_view(m.new_node, zone, incarnation, true);
         } else {
-            if m.ttl.as_u8() == self.options.passive_random_walk_len {
-                self.add_to_passive_view(m.new_node.clone());
+            if m.ttl.as_u16() == self.options.passive_random_walk_len {
+                trace_event!("added the new node to the passive view");
+                self.add_to_passive_view(m.new_node.clone(), zone, incarnation);
             }
-            if let Some(next) = self.select_forwarding_destination(&[&m.sender]) {
-                let message =
-                    ProtocolMessage::forward_join(&self.id, m.new_node, m.ttl.decrement());
+            // The last hop adds the new node to its active view.
+            // Ending the walk in another zone gives the new node a remote link,
+            // which the zone quotas can't provide otherwise.
+            let next_ttl = m.ttl.decrement();
+            let avoided_zone = zone.filter(|_| next_ttl.is_expired() && self.has_zone_quotas());
+            if let Some(next) = self.select_forwarding_destination(&[&m.sender], avoided_zone) {
+                trace_event!(destination = ?self.traced(&next), "forwarded the message");
+                let message = ProtocolMessage::forward_join(
+                    &self.id,
+                    m.new_node,
+                    zone,
+                    m.new_node_incarnation,
+                    next_ttl,
+                );
                 send(&mut self.actions, next, message);
             } else {
-                self.add_to_active_view(m.new_node, true);
+                trace_event!("no forwarding destination; added the new node to the active view");
+                self.add_to_active_view(m.new_node, zone, incarnation, true);
             }
         }
     }
 
-    fn handle_neighbor(&mut self, m: NeighborMessage<T>) {
-        if m.high_priority || !self.is_active_view_full() {
-            self.add_to_active_view(m.sender, false);
+    fn handle_neighbor(&mut self, m: NeighborMessage<SharedId<T>>) {
+        let zone = m.zone.or_else(|| self.known_zone(&m.sender));
+        if m.high_priority
+            || (!self.is_active_view_full() && !self.is_zone_quota_reached(zone, &[]))
+        {
+            let incarnation = Some(m.sender_incarnation);
+            self.add_to_active_view(m.sender, zone, incarnation, false);
         }
     }
 
-    fn handle_shuffle(&mut self, m: ShuffleMessage<T>) {
+    fn handle_shuffle(&mut self, m: ShuffleMessage<SharedId<T>>) {
+        trace_span!(
+            "handle_shuffle",
+            sender = ?self.traced(&m.sender),
+            origin = ?self.traced(&m.origin),
+            ttl = m.ttl.as_u16()
+        );
         if m.ttl.is_expired() {
-            self.passive_view.shuffle(&mut self.rng);
-            let reply_nodes = self
+            self.passive_view
+                .partial_shuffle(&mut self.rng, m.nodes.len());
+            let reply_nodes: Vec<_> = self
                 .passive_view
                 .iter()
                 .take(m.nodes.len())
                 .cloned()
                 .collect();
-            let message = ProtocolMessage::shuffle_reply(&self.id, reply_nodes);
+            let (zones, incarnations) = self.describe_nodes(&reply_nodes);
+            let tombstones = self.select_tombstones_for_gossip();
+            let message = ProtocolMessage::shuffle_reply(
+                &self.id,
+                reply_nodes,
+                zones,
+                incarnations,
+                tombstones,
+            );
+            trace_event!(nodes = ?self.traced_all(&m.nodes), "replied to the shuffle");
             send(&mut self.actions, m.origin.clone(), message);
-            self.add_shuffled_nodes_to_passive_view(m.nodes);
+            self.add_gossiped_tombstones(m.tombstones);
+            self.add_shuffled_nodes_to_passive_view(m.nodes, m.zones, m.incarnations);
         } else if let Some(destination) =
-            self.select_forwarding_destination(&[&m.origin, &m.sender])
+            self.select_forwarding_destination(&[&m.origin, &m.sender], None)
         {
-            let message = ProtocolMessage::shuffle(&self.id, m.origin, m.nodes, m.ttl.decrement());
+            let message = ProtocolMessage::shuffle(
+                &self.id,
+                m.origin,
+                m.nodes,
+                m.zones,
+                m.incarnations,
+                m.tombstones,
+                m.ttl.decrement(),
+            );
+            trace_event!(destination = ?self.traced(&destination), "forwarded the message");
             send(&mut self.actions, destination, message);
+        } else {
+            trace_event!("dropped the message: no forwarding destination");
         }
     }
 
-    fn handle_shuffle_reply(&mut self, m: ShuffleReplyMessage<T>) {
-        self.add_shuffled_nodes_to_passive_view(m.nodes);
+    fn handle_shuffle_reply(&mut self, m: ShuffleReplyMessage<SharedId<T>>) {
+        self.add_gossiped_tombstones(m.tombstones);
+        self.add_shuffled_nodes_to_passive_view(m.nodes, m.zones, m.incarnations);
     }
 
-    fn handle_disconnect(&mut self, m: DisconnectMessage<T>) {
+    fn handle_disconnect(&mut self, m: DisconnectMessage<SharedId<T>>) {
+        let rejected = self.reputation.as_mut().and_then(|r| {
+            let i = r.promotions.iter().position(|n| *n == m.sender)?;
+            Some(r.promotions.swap_remove(i))
+        });
+        if let Some(node) = rejected.filter(|n| !self.active_view.contains(n)) {
+            self.record_reputation(&node, ReputationEvent::RejectedNeighbor);
+        }
+
+        let zone = self.known_zone(&m.sender);
         if self.remove_from_active_view(&m.sender) {
             self.remove_from_passive_view(&m.sender);
             self.fill_active_view();
         }
         if m.alive {
-            self.add_to_passive_view(m.sender);
+            let incarnation = Some(m.sender_incarnation);
+            self.tombstones.unbury(&m.sender);
+            self.add_to_passive_view(m.sender, zone, incarnation);
+        } else if let Some(lifetime) = self.options.tombstone_lifetime {
+            self.remove_from_passive_view(&m.sender);
+            let expiry_time = self.now.saturating_add(lifetime);
+            let max_len = self.options.max_tombstones as usize;
+            self.tombstones
+                .bury(m.sender, m.sender_incarnation, expiry_time, max_len);
+        }
+    }
+
+    fn handle_optimization(&mut self, m: OptimizationMessage<SharedId<T>>) {
+        if self.active_view.contains(&m.sender) || m.sender == self.id {
+            // Already connected (e.g., a duplicate message).
+        } else if !self.is_active_view_full() {
+            let incarnation = Some(m.sender_incarnation);
+            self.insert_into_active_view(m.sender.clone(), None, incarnation);
+            let message = ProtocolMessage::optimization_reply(&self.id, m.old_node, true);
+            send(&mut self.actions, m.sender, message);
+            return;
+        } else if let Some(next) =
+            self.select_forwarding_destination(&[&m.sender, &m.old_node], None)
+        {
+            let message = ProtocolMessage::replace(&self.id, m.sender, m.old_node);
+            send(&mut self.actions, next, message);
+            return;
+        }
+
+        let message = ProtocolMessage::optimization_reply(&self.id, m.old_node, false);
+        send(&mut self.actions, m.sender.clone(), message);
+        self.disconnect_unless_active_view_node(m.sender);
+    }
+
+    fn handle_optimization_reply(&mut self, m: OptimizationReplyMessage<SharedId<T>>) {
+        let is_outstanding = self
+            .xbot
+            .as_mut()
+            .is_some_and(|xbot| xbot.finish_optimization(&m.sender, &m.old_node));
+        if !is_outstanding || !m.accepted {
+            // Replies that match no outstanding request (e.g., duplicate or late ones) are ignored
+            // except that the connection to the sender is closed.
+            self.disconnect_unless_active_view_node(m.sender);
+            return;
+        }
+        self.remove_from_active_view(&m.old_node);
+        let incarnation = Some(m.sender_incarnation);
+        if self.insert_into_active_view(m.sender.clone(), None, incarnation) {
+            if let Some(xbot) = self.xbot.as_mut() {
+                xbot.optimized.push(m.sender);
+            }
+        }
+    }
+
+    fn handle_replace(&mut self, m: ReplaceMessage<SharedId<T>>) {
+        let is_acceptable = m.old_node != self.id
+            && !self.active_view.contains(&m.old_node)
+            && self.active_view.contains(&m.sender)
+            && self.xbot.as_mut().is_some_and(|xbot| {
+                xbot.oracle.link_cost(&m.old_node) <= xbot.oracle.link_cost(&m.sender)
+            });
+        if is_acceptable {
+            let message = ProtocolMessage::switch(&self.id, m.origin, m.sender);
+            send(&mut self.actions, m.old_node, message);
+        } else {
+            let message = ProtocolMessage::replace_reply(&self.id, m.origin, m.old_node, false);
+            send(&mut self.actions, m.sender.clone(), message);
+            self.disconnect_unless_active_view_node(m.sender);
+        }
+    }
+
+    fn handle_replace_reply(&mut self, m: ReplaceReplyMessage<SharedId<T>>) {
+        if m.accepted {
+            self.detach_from_active_view(&m.sender);
+            self.insert_into_active_view(m.origin.clone(), None, None);
+        } else {
+            self.disconnect_unless_active_view_node(m.sender);
         }
+        let message = ProtocolMessage::optimization_reply(&self.id, m.old_node, m.accepted);
+        send(&mut self.actions, m.origin, message);
     }
 
-    fn add_shuffled_nodes_to_passive_view(&mut self, nodes: Vec<T>) {
-        for n in nodes {
-            self.add_to_passive_view(n);
+    fn handle_switch(&mut self, m: SwitchMessage<SharedId<T>>) {
+        let accepted = self.active_view.contains(&m.origin)
+            && !self.active_view.contains(&m.sender)
+            && m.sender != self.id;
+        if accepted {
+            self.detach_from_active_view(&m.origin);
+            let incarnation = Some(m.sender_incarnation);
+            self.insert_into_active_view(m.sender.clone(), None, incarnation);
+        }
+
+        let message = ProtocolMessage::switch_reply(&self.id, m.origin, m.candidate, accepted);
+        send(&mut self.actions, m.sender.clone(), message);
+        if !accepted {
+            self.disconnect_unless_active_view_node(m.sender);
+        }
+    }
+
+    fn handle_switch_reply(&mut self, m: SwitchReplyMessage<SharedId<T>>) {
+        if m.accepted {
+            self.detach_from_active_view(&m.candidate);
+            let incarnation = Some(m.sender_incarnation);
+            self.insert_into_active_view(m.sender.clone(), None, incarnation);
+        }
+
+        let message =
+            ProtocolMessage::replace_reply(&self.id, m.origin, m.sender.clone(), m.accepted);
+        send(&mut self.actions, m.candidate, message);
+        if !m.accepted {
+            self.disconnect_unless_active_view_node(m.sender);
         }
     }
 
-    fn add_to_active_view(&mut self, node: T, high_priority: bool) {
+    fn handle_try_later(&mut self, m: TryLaterMessage<SharedId<T>>) {
+        if m.kind == MessageKind::Join {
+            self.rejoin = Some((m.sender, self.now.saturating_add(m.retry_after)));
+        }
+    }
+
+    // Probing does not change the views, so the connection is closed without sending `DISCONNECT` message.
+    fn handle_ping(&mut self, m: PingMessage<SharedId<T>>) {
+        send(
+            &mut self.actions,
+            m.sender.clone(),
+            ProtocolMessage::pong(&self.id),
+        );
+        self.close_unless_active_view_node(m.sender);
+    }
+
+    fn handle_pong(&mut self, m: PongMessage<SharedId<T>>) {
+        if self.prober.as_mut().is_some_and(|p| p.finish(&m.sender)) {
+            let now = self.now;
+            if let Some(peer) = self.peers.get_mut(&m.sender) {
+                peer.last_confirmed = Some(now);
+            }
+        }
+        self.close_unless_active_view_node(m.sender);
+    }
+
+    fn add_shuffled_nodes_to_passive_view(
+        &mut self,
+        nodes: Vec<SharedId<T>>,
+        zones: Vec<Option<ZoneId>>,
+        incarnations: Vec<u64>,
+    ) {
+        let zones = zones.into_iter().chain(std::iter::repeat(None));
+        let incarnations = incarnations.into_iter().map(Some);
+        let incarnations = incarnations.chain(std::iter::repeat(None));
+        for ((n, zone), incarnation) in nodes.into_iter().zip(zones).zip(incarnations) {
+            self.add_to_passive_view(n, zone, incarnation);
+        }
+    }
+
+    fn add_to_active_view(
+        &mut self,
+        node: SharedId<T>,
+        zone: Option<ZoneId>,
+        incarnation: Option<u64>,
+        high_priority: bool,
+    ) {
+        self.remove_stale_active_view_entry(&node, incarnation);
         if self.active_view.contains(&node) || node == self.id {
             return;
         }
-        self.remove_random_from_active_view_if_full();
-        self.remove_from_passive_view(&node);
-        self.active_view.push(node.clone());
         send(
             &mut self.actions,
             node.clone(),
-            ProtocolMessage::neighbor(&self.id, high_priority),
+            ProtocolMessage::neighbor(&self.id, self.zone, high_priority),
         );
+        self.insert_into_active_view(node, zone, incarnation);
+    }
+
+    // Unlike `add_to_active_view`, this does not send `NEIGHBOR` message to `node`.
+    fn insert_into_active_view(
+        &mut self,
+        node: SharedId<T>,
+        zone: Option<ZoneId>,
+        incarnation: Option<u64>,
+    ) -> bool {
+        self.remove_stale_active_view_entry(&node, incarnation);
+        if self.active_view.contains(&node) || node == self.id {
+            return false;
+        }
+        let zone = zone.or_else(|| self.known_zone(&node));
+        let incarnation = incarnation.or_else(|| self.known_incarnation(&node));
+        self.tombstones.unbury(&node);
+        self.failures.record_success(&node);
+        self.make_room_in_active_view(zone);
+        if self.passive_view.contains(&node) {
+            self.remove_from_passive_view(&node);
+            self.metrics.promotions += 1;
+        }
+        if let Some(reputation) = self.reputation.as_mut() {
+            reputation.promotions.retain(|n| *n != node);
+        }
+        self.update_peer(&node, zone, incarnation);
+        self.active_view.push(node.clone());
         self.actions.push_back(Action::notify_up(node));
+        true
     }
 
-    fn add_to_passive_view(&mut self, node: T) {
-        if self.active_view.contains(&node) || self.passive_view.contains(&node) || node == self.id
-        {
+    // If `incarnation` is newer than the known one (i.e., `node` has restarted),
+    // removes the entry of `node` from the active view so that a fresh handshake takes place.
+    fn remove_stale_active_view_entry(&mut self, node: &SharedId<T>, incarnation: Option<u64>) {
+        if incarnation.is_none() || incarnation <= self.known_incarnation(node) {
             return;
         }
-        self.remove_random_from_passive_view_if_full();
+        if let Some(i) = self.active_view.position(node) {
+            self.active_view.swap_remove(i);
+            self.actions.push_back(Action::disconnect(node.clone()));
+            self.actions.push_back(Action::notify_down(node.clone()));
+        }
+    }
+
+    fn add_to_passive_view(
+        &mut self,
+        node: SharedId<T>,
+        zone: Option<ZoneId>,
+        incarnation: Option<u64>,
+    ) {
+        if self.passive_view.contains(&node) {
+            // Replaces the entry of an older incarnation.
+            let known = self.known_incarnation(&node);
+            if incarnation.is_some() && incarnation > known {
+                self.update_peer(&node, zone, incarnation);
+            }
+            return;
+        }
+        if self.active_view.contains(&node) || node == self.id {
+            return;
+        }
+        if self.tombstones.is_buried(&node, incarnation, self.now) {
+            return;
+        }
+        self.make_room_in_passive_view(Some(&node));
+        self.update_peer(&node, zone, incarnation);
+        if let Some(buckets) = self.passive_view_buckets.as_mut() {
+            buckets.insert(&node);
+        }
         self.passive_view.push(node);
     }
 
-    fn remove_from_active_view(&mut self, node: &T) -> bool {
-        let index = self.active_view.iter().position(|n| n == node);
+    fn add_gossiped_tombstones(&mut self, tombstones: Vec<Tombstone<SharedId<T>>>) {
+        let max_lifetime = match self.options.tombstone_lifetime {
+            None => return,
+            Some(lifetime) => lifetime,
+        };
+        let limit = self.options.max_gossiped_tombstones as usize;
+        let max_len = self.options.max_tombstones as usize;
+        for t in tombstones.into_iter().take(limit) {
+            if t.node == self.id || self.active_view.contains(&t.node) {
+                // Alive as far as the instance knows.
+                continue;
+            }
+            if self
+                .incarnation_of(&t.node)
+                .is_some_and(|x| x <= t.incarnation)
+            {
+                self.remove_from_passive_view(&t.node);
+            }
+            let expiry_time = self.now.saturating_add(t.lifetime.min(max_lifetime));
+            self.tombstones
+                .bury(t.node, t.incarnation, expiry_time, max_len);
+        }
+    }
+
+    fn select_tombstones_for_gossip(&self) -> Vec<Tombstone<SharedId<T>>> {
+        let limit = self.options.max_gossiped_tombstones as usize;
+        self.tombstones.select_for_gossip(limit, self.now)
+    }
+
+    fn record_reputation(&mut self, node: &SharedId<T>, event: ReputationEvent) {
+        let reputation = match self.reputation.as_mut() {
+            None => return,
+            Some(reputation) => reputation,
+        };
+        let weight = reputation.weighting.weight(&event);
+        let max_len = self.options.max_reputation_scores as usize;
+        reputation.add_score(node, weight, max_len);
+    }
+
+    fn record_active_uptime(&mut self, elapsed: Duration) {
+        let reputation = match self.reputation.as_mut() {
+            None => return,
+            Some(reputation) => reputation,
+        };
+        reputation.uptime_carry = reputation.uptime_carry.saturating_add(elapsed);
+        if reputation.uptime_carry < Duration::from_secs(1) {
+            return;
+        }
+        let uptime = std::mem::replace(&mut reputation.uptime_carry, Duration::from_secs(0));
+        let active_view = self.active_view.to_vec();
+        for node in &active_view {
+            self.record_reputation(node, ReputationEvent::ActiveUptime(uptime));
+        }
+    }
+
+    fn is_disreputable(&self, node: &SharedId<T>) -> bool {
+        self.reputation
+            .as_ref()
+            .is_some_and(|r| r.score(node).is_some_and(|s| s < 0))
+    }
+
+    // Selects the least reputable member of the active (or passive) view among `indices`
+    // if there are disreputable ones, otherwise selects a member randomly.
+    fn select_eviction_index<I>(&mut self, passive: bool, indices: I) -> Option<usize>
+    where
+        I: Iterator<Item = usize> + Clone,
+    {
+        if let Some(reputation) = self.reputation.as_ref() {
+            let view = if passive {
+                &self.passive_view
+            } else {
+                &self.active_view
+            };
+            let least_reputable = indices
+                .clone()
+                .filter(|&i| self.is_disreputable(&view[i]))
+                .min_by_key(|&i| reputation.score(&view[i]));
+            if least_reputable.is_some() {
+                return least_reputable;
+            }
+        }
+        indices.choose(&mut self.rng)
+    }
+
+    fn peer(&self, node: &SharedId<T>) -> Option<&PeerInfo<SharedId<T>>> {
+        self.peers.get(node)
+    }
+
+    fn known_zone(&self, node: &SharedId<T>) -> Option<ZoneId> {
+        if *node == self.id {
+            return self.zone;
+        }
+        self.peer(node).and_then(|p| p.zone)
+    }
+
+    fn known_incarnation(&self, node: &SharedId<T>) -> Option<u64> {
+        if *node == self.id {
+            return Some(self.incarnation);
+        }
+        self.peer(node).map(|p| p.incarnation)
+    }
+
+    // Unlike `IdInterner::intern`, this clones `node` only if it has not been interned.
+    fn intern(&mut self, node: &T) -> SharedId<T> {
+        match self.ids.get(node) {
+            Some(node) => node.clone(),
+            None => self.ids.intern(node.clone()),
+        }
+    }
+
+    fn update_peer(&mut self, node: &SharedId<T>, zone: Option<ZoneId>, incarnation: Option<u64>) {
+        if !self.peers.contains(node) {
+            self.peers.push(PeerInfo {
+                id: node.clone(),
+                zone: None,
+                incarnation: 0,
+                last_confirmed: None,
+            });
+        }
+        let peer = self.peers.get_mut(node).expect("never fails");
+        if zone.is_some() {
+            peer.zone = zone;
+        }
+        if let Some(incarnation) = incarnation {
+            let restarted = peer.incarnation < incarnation;
+            peer.incarnation = incarnation;
+            if restarted {
+                // A probe of the previous incarnation tells nothing about the new one.
+                self.cancel_probe(node);
+            }
+        }
+    }
+
+    fn cancel_probe(&mut self, node: &SharedId<T>) {
+        if let Some(prober) = self.prober.as_mut() {
+            prober.finish(node);
+        }
+    }
+
+    fn forget_peer_unless_in_views(&mut self, node: &SharedId<T>) {
+        if !self.active_view.contains(node) && !self.passive_view.contains(node) {
+            if let Some(i) = self.peers.position(node) {
+                self.peers.swap_remove(i);
+            }
+        }
+    }
+
+    fn describe_nodes(&self, nodes: &[SharedId<T>]) -> (Vec<Option<ZoneId>>, Vec<u64>) {
+        let zones = nodes.iter().map(|n| self.known_zone(n)).collect();
+        let incarnations = nodes
+            .iter()
+            .map(|n| self.known_incarnation(n).unwrap_or(0))
+            .collect();
+        (zones, incarnations)
+    }
+
+    fn zone_quota(&self, zone: ZoneId) -> Option<usize> {
+        if Some(zone) == self.zone {
+            self.options.max_local_zone_active_view_size
+        } else {
+            self.options.max_remote_zone_active_view_size
+        }
+        .map(usize::from)
+    }
+
+    fn has_zone_quotas(&self) -> bool {
+        self.options.max_local_zone_active_view_size.is_some()
+            || self.options.max_remote_zone_active_view_size.is_some()
+    }
+
+    // `pending` are the nodes to which promotions have been requested but not yet accepted.
+    fn is_zone_quota_reached(&self, zone: Option<ZoneId>, pending: &[SharedId<T>]) -> bool {
+        zone.and_then(|zone| {
+            self.zone_quota(zone).map(|quota| {
+                let members = self.active_view.iter().chain(pending.iter());
+                members.filter(|n| self.known_zone(n) == Some(zone)).count() >= quota
+            })
+        })
+        .unwrap_or(false)
+    }
+
+    fn find_zone_exceeding_quota(&self) -> Option<ZoneId> {
+        self.active_view
+            .iter()
+            .filter_map(|n| self.known_zone(n))
+            .find(|&zone| {
+                self.zone_quota(zone).is_some_and(|quota| {
+                    let members = self.active_view.iter();
+                    members.filter(|n| self.known_zone(n) == Some(zone)).count() > quota
+                })
+            })
+    }
+
+    fn make_room_in_active_view(&mut self, zone: Option<ZoneId>) {
+        if self.is_zone_quota_reached(zone, &[]) {
+            let same_zone_members = (0..self.active_view.len())
+                .filter(|&i| self.known_zone(&self.active_view[i]) == zone)
+                .collect::<Vec<_>>();
+            if let Some(i) = self.select_eviction_index(false, same_zone_members.into_iter()) {
+                trace_event!(
+                    node = ?self.traced(&self.active_view[i]),
+                    ?zone,
+                    "evicted from the active view: zone quota reached"
+                );
+                self.remove_from_active_view_by_index(i);
+                self.metrics.active_view_evictions += 1;
+                return;
+            }
+        }
+        self.remove_random_from_active_view_if_full();
+    }
+
+    fn remove_from_active_view(&mut self, node: &SharedId<T>) -> bool {
+        let index = self.active_view.position(node);
         if let Some(i) = index {
             self.remove_from_active_view_by_index(i);
             true
@@ -293,40 +1511,99 @@ where
     }
 
     fn remove_from_active_view_by_index(&mut self, i: usize) {
-        let node = self.active_view.swap_remove(i);
         send(
             &mut self.actions,
-            node.clone(),
+            self.active_view[i].clone(),
             ProtocolMessage::disconnect(&self.id, true),
         );
+        self.detach_from_active_view_by_index(i);
+    }
+
+    // Unlike `remove_from_active_view`, this does not send `DISCONNECT` message to `node`.
+    fn detach_from_active_view(&mut self, node: &SharedId<T>) -> bool {
+        let index = self.active_view.position(node);
+        if let Some(i) = index {
+            self.detach_from_active_view_by_index(i);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn detach_from_active_view_by_index(&mut self, i: usize) {
+        let node = self.active_view.swap_remove(i);
         self.actions.push_back(Action::disconnect(node.clone()));
         self.actions.push_back(Action::notify_down(node.clone()));
-        self.add_to_passive_view(node);
+        self.add_to_passive_view(node, None, None);
     }
 
     fn remove_random_from_active_view_if_full(&mut self) {
         if self.is_active_view_full() {
-            let i = self.rng.gen_range(0, self.active_view.len());
+            let i = self
+                .select_eviction_index(false, 0..self.active_view.len())
+                .expect("never fails");
+            trace_event!(
+                node = ?self.traced(&self.active_view[i]),
+                "evicted from the active view: view full"
+            );
             self.remove_from_active_view_by_index(i);
+            self.metrics.active_view_evictions += 1;
         }
     }
 
-    fn remove_from_passive_view(&mut self, node: &T) {
-        let position = self.passive_view.iter().position(|n| n == node);
+    fn remove_from_passive_view(&mut self, node: &SharedId<T>) {
+        let position = self.passive_view.position(node);
         if let Some(i) = position {
-            self.passive_view.swap_remove(i);
+            self.remove_from_passive_view_by_index(i);
+        }
+    }
+
+    fn remove_from_passive_view_by_index(&mut self, i: usize) {
+        let node = self.passive_view.swap_remove(i);
+        if let Some(buckets) = self.passive_view_buckets.as_mut() {
+            buckets.remove(&node);
+        }
+        self.cancel_probe(&node);
+        self.forget_peer_unless_in_views(&node);
+    }
+
+    // If `node` is `None`, only the overflow of the whole view is considered.
+    fn make_room_in_passive_view(&mut self, node: Option<&SharedId<T>>) {
+        if let Some(buckets) = self.passive_view_buckets.as_ref() {
+            let is_full = self.is_passive_view_full();
+            let victim = buckets
+                .select_eviction(node, is_full, &mut self.rng)
+                .and_then(|victim| self.passive_view.position(victim));
+            if let Some(i) = victim {
+                trace_event!(
+                    node = ?self.traced(&self.passive_view[i]),
+                    "evicted from the passive view: bucket full"
+                );
+                self.remove_from_passive_view_by_index(i);
+                self.metrics.passive_view_evictions += 1;
+            }
+        } else {
+            self.remove_random_from_passive_view_if_full();
         }
     }
 
     fn remove_random_from_passive_view_if_full(&mut self) {
         if self.is_passive_view_full() {
-            let i = self.rng.gen_range(0, self.passive_view.len());
-            self.passive_view.swap_remove(i);
+            let i = self
+                .select_eviction_index(true, 0..self.passive_view.len())
+                .expect("never fails");
+            trace_event!(
+                node = ?self.traced(&self.passive_view[i]),
+                "evicted from the passive view: view full"
+            );
+            self.remove_from_passive_view_by_index(i);
+            self.metrics.passive_view_evictions += 1;
         }
     }
 
-    fn disconnect_unless_active_view_node(&mut self, node: T) {
+    fn disconnect_unless_active_view_node(&mut self, node: SharedId<T>) {
         if !self.active_view.contains(&node) && self.id != node {
+            trace_event!(node = ?self.traced(&node), "disconnected the node not in the active view");
             send(
                 &mut self.actions,
                 node.clone(),
@@ -336,7 +1613,19 @@ where
         }
     }
 
-    fn select_forwarding_destination(&mut self, excludes: &[&T]) -> Option<T> {
+    fn close_unless_active_view_node(&mut self, node: SharedId<T>) {
+        if !self.active_view.contains(&node) && self.id != node {
+            self.actions.push_back(Action::disconnect(node));
+        }
+    }
+
+    // Selects a reputable member of the active view that is not in `excludes` if possible.
+    // Members in zones other than `avoided_zone` are preferred.
+    fn select_forwarding_destination(
+        &mut self,
+        excludes: &[&SharedId<T>],
+        avoided_zone: Option<ZoneId>,
+    ) -> Option<SharedId<T>> {
         let mut i = 0;
         let mut tail = self.active_view.len();
         while i < tail && tail != 0 {
@@ -349,15 +1638,31 @@ where
             }
         }
 
-        if tail == 0 {
-            None
-        } else {
-            let i = self.rng.gen_range(0, tail);
-            Some(self.active_view[i].clone())
+        let mut candidates = (0..tail)
+            .filter(|&i| !self.is_disreputable(&self.active_view[i]))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            candidates = (0..tail).collect();
+        }
+        if let Some(zone) = avoided_zone {
+            let remote = candidates
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    self.known_zone(&self.active_view[i])
+                        .is_some_and(|z| z != zone)
+                })
+                .collect::<Vec<_>>();
+            if !remote.is_empty() {
+                candidates = remote;
+            }
         }
+        candidates
+            .choose(&mut self.rng)
+            .map(|&i| self.active_view[i].clone())
     }
 
-    fn select_random_from_active_view(&mut self) -> Option<T> {
+    fn select_random_from_active_view(&mut self) -> Option<SharedId<T>> {
         if self.active_view.is_empty() {
             None
         } else {
@@ -366,16 +1671,106 @@ where
         }
     }
 
-    fn select_random_from_passive_view(&mut self) -> Option<T> {
-        if self.passive_view.is_empty() {
-            None
-        } else {
-            let i = self.rng.gen_range(0, self.passive_view.len());
-            Some(self.passive_view[i].clone())
+    fn is_overloaded_by_joins(&mut self) -> bool {
+        let limit = match self.options.join_redirect_limit.as_ref() {
+            None => return false,
+            Some(limit) => limit,
+        };
+        let now = self.now;
+        let bucket = self
+            .join_bucket
+            .get_or_insert_with(|| TokenBucket::new(limit, now));
+        bucket.acquire(limit, now).is_err()
+    }
+
+    fn select_redirect_contacts(&mut self, new_node: &SharedId<T>) -> Vec<SharedId<T>> {
+        let pv_size = self.options.shuffle_passive_view_size as usize;
+        self.passive_view.partial_shuffle(&mut self.rng, pv_size);
+
+        let active_nodes = self.active_view.iter();
+        let passive_nodes = self.passive_view.iter().take(pv_size);
+        active_nodes
+            .chain(passive_nodes)
+            .filter(|n| *n != new_node)
+            .cloned()
+            .collect()
+    }
+
+    fn sample_views(&mut self, excluded: &SharedId<T>) -> Vec<SharedId<T>> {
+        let pv_size = self.options.shuffle_passive_view_size as usize;
+        let av_size = self.options.shuffle_active_view_size as usize;
+        // One extra entry is sampled from each view in case it is `excluded`.
+        self.passive_view
+            .partial_shuffle(&mut self.rng, pv_size + 1);
+        self.active_view.partial_shuffle(&mut self.rng, av_size + 1);
+
+        let active_nodes = self.active_view.iter().filter(|n| *n != excluded);
+        let passive_nodes = self.passive_view.iter().filter(|n| *n != excluded);
+        active_nodes
+            .take(av_size)
+            .chain(passive_nodes.take(pv_size))
+            .cloned()
+            .collect()
+    }
+
+    fn request_promotions(&mut self, count: usize) {
+        let mut requested = Vec::new();
+        while requested.len() < count {
+            match self.select_promotion_candidate(&requested) {
+                Some(node) => requested.push(node),
+                None => break,
+            }
         }
+        if requested.is_empty() {
+            return;
+        }
+
+        let high_priority = self.active_view.is_empty();
+        if let Some(reputation) = self.reputation.as_mut().filter(|_| !high_priority) {
+            reputation.promotions = requested.clone();
+        }
+        for node in requested {
+            let message = ProtocolMessage::neighbor(&self.id, self.zone, high_priority);
+            send(&mut self.actions, node, message);
+        }
+    }
+
+    fn select_promotion_candidate(&mut self, requested: &[SharedId<T>]) -> Option<SharedId<T>> {
+        let ignore_quota = self.active_view.is_empty();
+        let mut candidates = self
+            .passive_view
+            .iter()
+            .filter(|n| !requested.contains(n))
+            .filter(|n| !self.failures.is_backing_off(n, self.now))
+            .filter(|n| ignore_quota || !self.is_zone_quota_reached(self.known_zone(n), requested))
+            .collect::<Vec<_>>();
+        if let Some(prober) = self.prober.as_ref() {
+            let max_age = prober.options.max_confirmation_age;
+            let now = self.now;
+            let is_recently_confirmed = |n: &SharedId<T>| {
+                self.peer(n)
+                    .and_then(|p| p.last_confirmed)
+                    .is_some_and(|t| now - t <= max_age)
+            };
+            if candidates.iter().any(|n| is_recently_confirmed(n)) {
+                candidates.retain(|n| is_recently_confirmed(n));
+            }
+        }
+        if candidates.iter().any(|n| !self.is_disreputable(n)) {
+            candidates.retain(|n| !self.is_disreputable(n));
+        }
+        candidates.choose(&mut self.rng).map(|n| (*n).clone())
     }
 }
 
+#[derive(Debug)]
+struct PeerInfo<T> {
+    id: T,
+    zone: Option<ZoneId>,
+    incarnation: u64,
+    last_confirmed: Option<Duration>,
+}
+
 fn send<T>(actions: &mut VecDeque<Action<T>>, destination: T, message: ProtocolMessage<T>) {
     actions.push_back(Action::send(destination, message));
 }
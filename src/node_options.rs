@@ -1,54 +1,222 @@
+use crate::{BackoffOptions, RateLimit, RateLimitOptions};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
 /// Options for HyParView [Node](./struct.Node.html).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct NodeOptions {
     /// Maximum number of nodes in the active view.
-    pub max_active_view_size: u8,
+    pub max_active_view_size: u16,
 
     /// Maximum number of nodes in the passive view.
-    pub max_passive_view_size: u8,
+    pub max_passive_view_size: u16,
 
     /// Protocol parameter that is called `ka` in the [paper].
     ///
     /// [paper]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
-    pub shuffle_active_view_size: u8,
+    pub shuffle_active_view_size: u16,
 
     /// Protocol parameter that is called `kp` in the [paper].
     ///
     /// [paper]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
-    pub shuffle_passive_view_size: u8,
+    pub shuffle_passive_view_size: u16,
 
     /// Protocol parameter that is called `ARWL` in the [paper].
     ///
     /// This is the initial TTL value for `ForwardJoin` and `Shuffle` messages.
     ///
     /// [paper]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
-    pub active_random_walk_len: u8,
+    pub active_random_walk_len: u16,
 
     /// Protocol parameter that is called `PRWL` in the [paper].
     ///
     /// If TTL is equal to the value,
     ///
     /// [paper]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
-    pub passive_random_walk_len: u8,
+    pub passive_random_walk_len: u16,
+
+    /// Maximum number of nodes in the active view that belong to the same zone as the local node.
+    ///
+    /// `None` means that there is no limit.
+    /// This is effective only if the zone of the local node has been set by `Node::set_zone` method.
+    pub max_local_zone_active_view_size: Option<u16>,
+
+    /// Maximum number of nodes in the active view that belong to each of the remote zones.
+    ///
+    /// `None` means that there is no limit.
+    /// Nodes whose zones are unknown are not counted.
+    ///
+    /// If either of the zone quotas is set, the last hop of a `FORWARD_JOIN` random walk is
+    /// preferably selected from the zones other than that of the joining node,
+    /// so that the joining node gets links to remote zones.
+    pub max_remote_zone_active_view_size: Option<u16>,
+
+    /// Per-peer rate limits of incoming messages.
+    ///
+    /// The limits are refilled according to the clock advanced by `Node::advance_clock` method.
+    pub rate_limits: RateLimitOptions,
+
+    /// Limit of the number of `JOIN` messages absorbed by the instance as a contact node.
+    ///
+    /// `JOIN` messages exceeding the limit are redirected to other nodes in the views
+    /// by replying `JOIN_REDIRECT` messages.
+    /// `None` means that `JOIN` messages are never redirected.
+    pub join_redirect_limit: Option<RateLimit>,
+
+    /// Lifetime of the tombstones of dead nodes.
+    ///
+    /// A node that left with `alive: false` disconnection (or was detected as failed)
+    /// is not added to the passive view again until its tombstone expires,
+    /// unless a newer incarnation of the node appears.
+    /// `None` means that tombstones are disabled.
+    #[cfg_attr(feature = "serde", serde(with = "crate::seconds::option"))]
+    pub tombstone_lifetime: Option<Duration>,
+
+    /// Maximum number of tombstones piggybacked on a `SHUFFLE` or `SHUFFLE_REPLY` message.
+    ///
+    /// This is also the maximum number of tombstones accepted from a received message (excess ones are ignored).
+    ///
+    /// If it is zero, tombstones are not gossiped.
+    pub max_gossiped_tombstones: u16,
+
+    /// Maximum number of tombstones held by a node.
+    ///
+    /// If the limit is reached, the tombstone expiring first is replaced by a new one.
+    pub max_tombstones: u16,
+
+    /// Maximum number of reputation scores held by a node.
+    ///
+    /// The scores are kept regardless of the membership of the views,
+    /// so that disreputable peers can't reset their scores by leaving and rejoining the views.
+    /// If the limit is reached, the score closest to zero is evicted.
+    pub max_reputation_scores: u16,
+
+    /// Backoff of the peers reported as unreachable by `Node::report_unreachable` method.
+    pub failure_backoff: BackoffOptions,
 }
 impl NodeOptions {
     /// The default value of `max_active_view_size` field.
-    pub const DEFAULT_MAX_ACTIVE_VIEW_SIZE: u8 = 4;
+    pub const DEFAULT_MAX_ACTIVE_VIEW_SIZE: u16 = 4;
 
     /// The default value of `max_passive_view_size` field.
-    pub const DEFAULT_MAX_PASSIVE_VIEW_SIZE: u8 = 24;
+    pub const DEFAULT_MAX_PASSIVE_VIEW_SIZE: u16 = 24;
 
     /// The default value of `shuffle_active_view_size` field.
-    pub const DEFAULT_SHUFFLE_ACTIVE_VIEW_SIZE: u8 = 2;
+    pub const DEFAULT_SHUFFLE_ACTIVE_VIEW_SIZE: u16 = 2;
 
     /// The default value of `shuffle_passive_view_size` field.
-    pub const DEFAULT_SHUFFLE_PASSIVE_VIEW_SIZE: u8 = 2;
+    pub const DEFAULT_SHUFFLE_PASSIVE_VIEW_SIZE: u16 = 2;
 
     /// The default value of `active_random_walk_len` field.
-    pub const DEFAULT_ACTIVE_RANDOM_WALK_LEN: u8 = 5;
+    pub const DEFAULT_ACTIVE_RANDOM_WALK_LEN: u16 = 5;
 
     /// The default value of `passive_random_walk_len` field.
-    pub const DEFAULT_PASSIVE_RANDOM_WALK_LEN: u8 = 2;
+    pub const DEFAULT_PASSIVE_RANDOM_WALK_LEN: u16 = 2;
+
+    /// The default value of `max_tombstones` field.
+    pub const DEFAULT_MAX_TOMBSTONES: u16 = 256;
+
+    /// The default value of `max_reputation_scores` field.
+    pub const DEFAULT_MAX_REPUTATION_SCORES: u16 = 1024;
+
+    /// Upper bound of the durations in the options.
+    pub const MAX_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+    /// Validates the consistency of the options.
+    ///
+    /// This is done by `NodeOptionsBuilder::build` and `Node::apply_options` methods.
+    /// If there are multiple inconsistencies, the first one reported by `NodeOptions::errors` is returned.
+    pub fn validate(&self) -> Result<(), NodeOptionsError> {
+        match self.errors().into_iter().next() {
+            None => Ok(()),
+            Some(e) => Err(e),
+        }
+    }
+
+    /// Returns all the inconsistencies of the options.
+    pub fn errors(&self) -> Vec<NodeOptionsError> {
+        let mut errors = Vec::new();
+        if self.max_active_view_size == 0 {
+            errors.push(NodeOptionsError::ZeroActiveViewSize);
+        }
+        if self.max_passive_view_size == 0 {
+            errors.push(NodeOptionsError::ZeroPassiveViewSize);
+        }
+        if self.shuffle_active_view_size > self.max_active_view_size {
+            errors.push(NodeOptionsError::ShuffleActiveViewSizeTooLarge {
+                shuffle_active_view_size: self.shuffle_active_view_size,
+                max_active_view_size: self.max_active_view_size,
+            });
+        }
+        if self.shuffle_passive_view_size > self.max_passive_view_size {
+            errors.push(NodeOptionsError::ShufflePassiveViewSizeTooLarge {
+                shuffle_passive_view_size: self.shuffle_passive_view_size,
+                max_passive_view_size: self.max_passive_view_size,
+            });
+        }
+        if self.passive_random_walk_len > self.active_random_walk_len {
+            errors.push(NodeOptionsError::PassiveRandomWalkLenTooLarge {
+                passive_random_walk_len: self.passive_random_walk_len,
+                active_random_walk_len: self.active_random_walk_len,
+            });
+        }
+        if self.max_local_zone_active_view_size == Some(0) {
+            errors.push(NodeOptionsError::ZeroLocalZoneQuota);
+        }
+        if self.max_remote_zone_active_view_size == Some(0) {
+            errors.push(NodeOptionsError::ZeroRemoteZoneQuota);
+        }
+        if let (Some(local), Some(remote)) = (
+            self.max_local_zone_active_view_size,
+            self.max_remote_zone_active_view_size,
+        ) {
+            if u32::from(local) + u32::from(remote) < u32::from(self.max_active_view_size) {
+                errors.push(NodeOptionsError::ZoneQuotasTooSmall {
+                    max_local_zone_active_view_size: local,
+                    max_remote_zone_active_view_size: remote,
+                    max_active_view_size: self.max_active_view_size,
+                });
+            }
+        }
+        let backoff = &self.failure_backoff;
+        if backoff.initial_backoff > backoff.max_backoff {
+            errors.push(NodeOptionsError::InitialBackoffTooLarge {
+                initial_backoff: backoff.initial_backoff,
+                max_backoff: backoff.max_backoff,
+            });
+        }
+
+        let limits = &self.rate_limits;
+        let durations = [
+            ("failure_backoff.max_backoff", Some(backoff.max_backoff)),
+            ("tombstone_lifetime", self.tombstone_lifetime),
+            (
+                "rate_limits.join.refill_interval",
+                limits.join.as_ref().map(|l| l.refill_interval),
+            ),
+            (
+                "rate_limits.forward_join.refill_interval",
+                limits.forward_join.as_ref().map(|l| l.refill_interval),
+            ),
+            (
+                "rate_limits.shuffle.refill_interval",
+                limits.shuffle.as_ref().map(|l| l.refill_interval),
+            ),
+            (
+                "join_redirect_limit.refill_interval",
+                self.join_redirect_limit.as_ref().map(|l| l.refill_interval),
+            ),
+        ];
+        for &(field, duration) in &durations {
+            if let Some(duration) = duration.filter(|&d| d > Self::MAX_DURATION) {
+                errors.push(NodeOptionsError::DurationTooLarge { field, duration });
+            }
+        }
+        errors
+    }
 }
 impl Default for NodeOptions {
     fn default() -> Self {
@@ -59,6 +227,331 @@ impl Default for NodeOptions {
             shuffle_passive_view_size: Self::DEFAULT_SHUFFLE_PASSIVE_VIEW_SIZE,
             active_random_walk_len: Self::DEFAULT_ACTIVE_RANDOM_WALK_LEN,
             passive_random_walk_len: Self::DEFAULT_PASSIVE_RANDOM_WALK_LEN,
+            max_local_zone_active_view_size: None,
+            max_remote_zone_active_view_size: None,
+            rate_limits: RateLimitOptions::default(),
+            join_redirect_limit: None,
+            tombstone_lifetime: None,
+            max_gossiped_tombstones: 0,
+            max_tombstones: Self::DEFAULT_MAX_TOMBSTONES,
+            max_reputation_scores: Self::DEFAULT_MAX_REPUTATION_SCORES,
+            failure_backoff: BackoffOptions::default(),
+        }
+    }
+}
+
+/// Builder of validated [NodeOptions](./struct.NodeOptions.html).
+///
+/// # Examples
+///
+/// ```
+/// use hyparview::{NodeOptionsBuilder, NodeOptionsError};
+///
+/// let options = NodeOptionsBuilder::for_cluster_size(10_000).build().unwrap();
+/// assert_eq!(options.max_active_view_size, 5);
+/// assert_eq!(options.max_passive_view_size, 30);
+///
+/// let error = NodeOptionsBuilder::new().max_active_view_size(0).build().unwrap_err();
+/// assert_eq!(error, NodeOptionsError::ZeroActiveViewSize);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NodeOptionsBuilder {
+    options: NodeOptions,
+}
+impl NodeOptionsBuilder {
+    /// Makes a new `NodeOptionsBuilder` instance with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new `NodeOptionsBuilder` instance with the options suited for a cluster of `cluster_size` nodes.
+    ///
+    /// As recommended in the [paper], the size of the active view is `log10(cluster_size) + 1`
+    /// and the size of the passive view is six times that.
+    /// The random walk lengths are also scaled as in the experiments of the paper
+    /// (`ARWL` is the active view size plus one, and `PRWL` is half of it).
+    /// The shuffle sizes are clamped to the view sizes, so the resulting options are always valid.
+    ///
+    /// [paper]: http://asc.di.fct.unl.pt/~jleitao/pdf/dsn07-leitao.pdf
+    pub fn for_cluster_size(cluster_size: usize) -> Self {
+        let mut log = 0u16;
+        let mut n = 1usize;
+        while n < cluster_size {
+            n = n.saturating_mul(10);
+            log += 1;
+        }
+        let active_view_size = log + 1;
+        let passive_view_size = active_view_size.saturating_mul(6);
+        let active_random_walk_len = active_view_size + 1;
+        let mut this = Self::new();
+        let shuffle_active_view_size = this.options.shuffle_active_view_size.min(active_view_size);
+        let shuffle_passive_view_size = this
+            .options
+            .shuffle_passive_view_size
+            .min(passive_view_size);
+        this.max_active_view_size(active_view_size)
+            .max_passive_view_size(passive_view_size)
+            .shuffle_active_view_size(shuffle_active_view_size)
+            .shuffle_passive_view_size(shuffle_passive_view_size)
+            .active_random_walk_len(active_random_walk_len)
+            .passive_random_walk_len(active_random_walk_len / 2);
+        this
+    }
+
+    /// Sets `NodeOptions::max_active_view_size`.
+    pub fn max_active_view_size(&mut self, size: u16) -> &mut Self {
+        self.options.max_active_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::max_passive_view_size`.
+    pub fn max_passive_view_size(&mut self, size: u16) -> &mut Self {
+        self.options.max_passive_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::shuffle_active_view_size`.
+    pub fn shuffle_active_view_size(&mut self, size: u16) -> &mut Self {
+        self.options.shuffle_active_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::shuffle_passive_view_size`.
+    pub fn shuffle_passive_view_size(&mut self, size: u16) -> &mut Self {
+        self.options.shuffle_passive_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::active_random_walk_len`.
+    pub fn active_random_walk_len(&mut self, len: u16) -> &mut Self {
+        self.options.active_random_walk_len = len;
+        self
+    }
+
+    /// Sets `NodeOptions::passive_random_walk_len`.
+    pub fn passive_random_walk_len(&mut self, len: u16) -> &mut Self {
+        self.options.passive_random_walk_len = len;
+        self
+    }
+
+    /// Sets `NodeOptions::max_local_zone_active_view_size`.
+    pub fn max_local_zone_active_view_size(&mut self, size: Option<u16>) -> &mut Self {
+        self.options.max_local_zone_active_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::max_remote_zone_active_view_size`.
+    pub fn max_remote_zone_active_view_size(&mut self, size: Option<u16>) -> &mut Self {
+        self.options.max_remote_zone_active_view_size = size;
+        self
+    }
+
+    /// Sets `NodeOptions::rate_limits`.
+    pub fn rate_limits(&mut self, limits: RateLimitOptions) -> &mut Self {
+        self.options.rate_limits = limits;
+        self
+    }
+
+    /// Sets `NodeOptions::join_redirect_limit`.
+    pub fn join_redirect_limit(&mut self, limit: Option<RateLimit>) -> &mut Self {
+        self.options.join_redirect_limit = limit;
+        self
+    }
+
+    /// Sets `NodeOptions::tombstone_lifetime`.
+    pub fn tombstone_lifetime(&mut self, lifetime: Option<Duration>) -> &mut Self {
+        self.options.tombstone_lifetime = lifetime;
+        self
+    }
+
+    /// Sets `NodeOptions::max_gossiped_tombstones`.
+    pub fn max_gossiped_tombstones(&mut self, n: u16) -> &mut Self {
+        self.options.max_gossiped_tombstones = n;
+        self
+    }
+
+    /// Sets `NodeOptions::max_tombstones`.
+    pub fn max_tombstones(&mut self, n: u16) -> &mut Self {
+        self.options.max_tombstones = n;
+        self
+    }
+
+    /// Sets `NodeOptions::max_reputation_scores`.
+    pub fn max_reputation_scores(&mut self, n: u16) -> &mut Self {
+        self.options.max_reputation_scores = n;
+        self
+    }
+
+    /// Sets `NodeOptions::failure_backoff`.
+    pub fn failure_backoff(&mut self, backoff: BackoffOptions) -> &mut Self {
+        self.options.failure_backoff = backoff;
+        self
+    }
+
+    /// Validates and builds the options.
+    pub fn build(&self) -> Result<NodeOptions, NodeOptionsError> {
+        self.options.validate()?;
+        Ok(self.options.clone())
+    }
+}
+
+/// Inconsistency of [NodeOptions](./struct.NodeOptions.html) detected by `NodeOptions::validate` method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeOptionsError {
+    /// `max_active_view_size` is zero.
+    ZeroActiveViewSize,
+
+    /// `max_passive_view_size` is zero.
+    ZeroPassiveViewSize,
+
+    /// `shuffle_active_view_size` is greater than `max_active_view_size`.
+    ShuffleActiveViewSizeTooLarge {
+        /// The value of `shuffle_active_view_size`.
+        shuffle_active_view_size: u16,
+
+        /// The value of `max_active_view_size`.
+        max_active_view_size: u16,
+    },
+
+    /// `shuffle_passive_view_size` is greater than `max_passive_view_size`.
+    ShufflePassiveViewSizeTooLarge {
+        /// The value of `shuffle_passive_view_size`.
+        shuffle_passive_view_size: u16,
+
+        /// The value of `max_passive_view_size`.
+        max_passive_view_size: u16,
+    },
+
+    /// `passive_random_walk_len` is greater than `active_random_walk_len`.
+    ///
+    /// In that case, `FORWARD_JOIN` messages never add the new node to passive views.
+    PassiveRandomWalkLenTooLarge {
+        /// The value of `passive_random_walk_len`.
+        passive_random_walk_len: u16,
+
+        /// The value of `active_random_walk_len`.
+        active_random_walk_len: u16,
+    },
+
+    /// `failure_backoff.initial_backoff` is greater than `failure_backoff.max_backoff`.
+    InitialBackoffTooLarge {
+        /// The value of `failure_backoff.initial_backoff`.
+        initial_backoff: Duration,
+
+        /// The value of `failure_backoff.max_backoff`.
+        max_backoff: Duration,
+    },
+
+    /// `max_local_zone_active_view_size` is `Some(0)`.
+    ZeroLocalZoneQuota,
+
+    /// `max_remote_zone_active_view_size` is `Some(0)`.
+    ZeroRemoteZoneQuota,
+
+    /// The sum of the zone quotas is less than `max_active_view_size`.
+    ///
+    /// In that case, the active view can't be filled if there is only one remote zone.
+    ZoneQuotasTooSmall {
+        /// The value of `max_local_zone_active_view_size`.
+        max_local_zone_active_view_size: u16,
+
+        /// The value of `max_remote_zone_active_view_size`.
+        max_remote_zone_active_view_size: u16,
+
+        /// The value of `max_active_view_size`.
+        max_active_view_size: u16,
+    },
+
+    /// A duration is greater than `NodeOptions::MAX_DURATION`.
+    DurationTooLarge {
+        /// The name of the field.
+        field: &'static str,
+
+        /// The value of the field.
+        duration: Duration,
+    },
+}
+impl NodeOptionsError {
+    /// Returns the name of the offending field.
+    pub fn field(&self) -> &'static str {
+        match self {
+            NodeOptionsError::ZeroActiveViewSize => "max_active_view_size",
+            NodeOptionsError::ZeroPassiveViewSize => "max_passive_view_size",
+            NodeOptionsError::ShuffleActiveViewSizeTooLarge { .. } => "shuffle_active_view_size",
+            NodeOptionsError::ShufflePassiveViewSizeTooLarge { .. } => "shuffle_passive_view_size",
+            NodeOptionsError::PassiveRandomWalkLenTooLarge { .. } => "passive_random_walk_len",
+            NodeOptionsError::InitialBackoffTooLarge { .. } => "failure_backoff.initial_backoff",
+            NodeOptionsError::ZeroLocalZoneQuota => "max_local_zone_active_view_size",
+            NodeOptionsError::ZeroRemoteZoneQuota => "max_remote_zone_active_view_size",
+            NodeOptionsError::ZoneQuotasTooSmall { .. } => "max_local_zone_active_view_size",
+            NodeOptionsError::DurationTooLarge { field, .. } => field,
+        }
+    }
+}
+impl fmt::Display for NodeOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeOptionsError::ZeroActiveViewSize => {
+                write!(f, "`max_active_view_size` must be positive")
+            }
+            NodeOptionsError::ZeroPassiveViewSize => {
+                write!(f, "`max_passive_view_size` must be positive")
+            }
+            NodeOptionsError::ShuffleActiveViewSizeTooLarge {
+                shuffle_active_view_size,
+                max_active_view_size,
+            } => write!(
+                f,
+                "`shuffle_active_view_size` ({}) must not be greater than `max_active_view_size` ({})",
+                shuffle_active_view_size, max_active_view_size
+            ),
+            NodeOptionsError::ShufflePassiveViewSizeTooLarge {
+                shuffle_passive_view_size,
+                max_passive_view_size,
+            } => write!(
+                f,
+                "`shuffle_passive_view_size` ({}) must not be greater than `max_passive_view_size` ({})",
+                shuffle_passive_view_size, max_passive_view_size
+            ),
+            NodeOptionsError::PassiveRandomWalkLenTooLarge {
+                passive_random_walk_len,
+                active_random_walk_len,
+            } => write!(
+                f,
+                "`passive_random_walk_len` ({}) must not be greater than `active_random_walk_len` ({})",
+                passive_random_walk_len, active_random_walk_len
+            ),
+            NodeOptionsError::InitialBackoffTooLarge {
+                initial_backoff,
+                max_backoff,
+            } => write!(
+                f,
+                "`failure_backoff.initial_backoff` ({:?}) must not be greater than `failure_backoff.max_backoff` ({:?})",
+                initial_backoff, max_backoff
+            ),
+            NodeOptionsError::ZeroLocalZoneQuota => {
+                write!(f, "`max_local_zone_active_view_size` must be positive")
+            }
+            NodeOptionsError::ZeroRemoteZoneQuota => {
+                write!(f, "`max_remote_zone_active_view_size` must be positive")
+            }
+            NodeOptionsError::ZoneQuotasTooSmall {
+                max_local_zone_active_view_size,
+                max_remote_zone_active_view_size,
+                max_active_view_size,
+            } => write!(
+                f,
+                "the sum of `max_local_zone_active_view_size` ({}) and `max_remote_zone_active_view_size` ({}) must not be less than `max_active_view_size` ({})",
+                max_local_zone_active_view_size, max_remote_zone_active_view_size, max_active_view_size
+            ),
+            NodeOptionsError::DurationTooLarge { field, duration } => write!(
+                f,
+                "`{}` ({:?}) must not be greater than {:?}",
+                field,
+                duration,
+                NodeOptions::MAX_DURATION
+            ),
         }
     }
 }
+impl Error for NodeOptionsError {}
@@ -0,0 +1,93 @@
+use crate::view::IndexedVec;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Options for the liveness probing of the passive view of HyParView [Node](./struct.Node.html).
+#[derive(Debug, Clone)]
+pub struct ProbeOptions {
+    /// Number of passive view members probed by each `Node::probe_passive_view` call.
+    pub probes_per_round: u8,
+
+    /// Timeout of `PING` messages.
+    ///
+    /// Nodes that do not reply `PONG` messages within the timeout are removed from the passive view.
+    pub timeout: Duration,
+
+    /// Maximum age of liveness confirmations that are regarded as recent.
+    ///
+    /// Recently confirmed nodes are preferred when promoting a node to the active view.
+    pub max_confirmation_age: Duration,
+}
+impl ProbeOptions {
+    /// The default value of `probes_per_round` field.
+    pub const DEFAULT_PROBES_PER_ROUND: u8 = 2;
+
+    /// The default value of `timeout` field.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// The default value of `max_confirmation_age` field.
+    pub const DEFAULT_MAX_CONFIRMATION_AGE: Duration = Duration::from_secs(60);
+}
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        ProbeOptions {
+            probes_per_round: Self::DEFAULT_PROBES_PER_ROUND,
+            timeout: Self::DEFAULT_TIMEOUT,
+            max_confirmation_age: Self::DEFAULT_MAX_CONFIRMATION_AGE,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Prober<T> {
+    pub options: ProbeOptions,
+
+    // (node, deadline)
+    pending: IndexedVec<T, (T, Duration)>,
+}
+impl<T> Prober<T>
+where
+    T: Clone + Eq + Hash,
+{
+    pub fn new(options: ProbeOptions) -> Self {
+        Prober {
+            options,
+            pending: IndexedVec::with_key(0, |x| &x.0),
+        }
+    }
+
+    pub fn is_pending(&self, node: &T) -> bool {
+        self.pending.contains(node)
+    }
+
+    pub fn start(&mut self, node: T, now: Duration) {
+        if !self.pending.contains(&node) {
+            let deadline = now.saturating_add(self.options.timeout);
+            self.pending.push((node, deadline));
+        }
+    }
+
+    /// Returns `true` if there was a pending probe for `node`.
+    pub fn finish(&mut self, node: &T) -> bool {
+        if let Some(i) = self.pending.position(node) {
+            self.pending.swap_remove(i);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the timed out probes and returns their target nodes.
+    pub fn take_timed_out(&mut self, now: Duration) -> Vec<T> {
+        let mut timed_out = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            if self.pending[i].1 <= now {
+                timed_out.push(self.pending.swap_remove(i).0);
+            } else {
+                i += 1;
+            }
+        }
+        timed_out
+    }
+}
@@ -0,0 +1,259 @@
+//! Prometheus text exposition of [NodeMetrics](../struct.NodeMetrics.html).
+//!
+//! This module is available only if the `prometheus` feature is enabled.
+use crate::message::MessageKind;
+use crate::NodeMetrics;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write};
+
+/// Labels attached to every sample of a metrics family.
+///
+/// These are used for distinguishing several overlays running in the same process.
+/// The names are validated when the labels are made, so the rendered exposition is always well-formed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels {
+    pairs: Vec<(String, String)>,
+}
+impl Labels {
+    /// The name of the label distinguishing the kinds of messages.
+    ///
+    /// It is attached by this module, so it can't be used by users.
+    pub const RESERVED_KIND: &'static str = "kind";
+
+    /// Makes a new `Labels` instance from pairs of a label name and its value.
+    ///
+    /// Each name must match `[a-zA-Z_][a-zA-Z0-9_]*`, must not start with `__` (reserved by Prometheus),
+    /// must not be `kind` (see `Labels::RESERVED_KIND`), and must not appear more than once.
+    pub fn new(pairs: &[(&str, &str)]) -> Result<Self, LabelError> {
+        let mut labels = Labels::default();
+        for &(name, value) in pairs {
+            if !is_valid_label_name(name) {
+                return Err(LabelError::InvalidName(name.to_owned()));
+            }
+            if name.starts_with("__") || name == Self::RESERVED_KIND {
+                return Err(LabelError::ReservedName(name.to_owned()));
+            }
+            if labels.pairs.iter().any(|(n, _)| n == name) {
+                return Err(LabelError::DuplicateName(name.to_owned()));
+            }
+            labels.pairs.push((name.to_owned(), value.to_owned()));
+        }
+        Ok(labels)
+    }
+}
+
+/// Error returned by `Labels::new` method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelError {
+    /// The name does not match `[a-zA-Z_][a-zA-Z0-9_]*`.
+    InvalidName(String),
+
+    /// The name is reserved by Prometheus or this module.
+    ReservedName(String),
+
+    /// The name appears more than once.
+    DuplicateName(String),
+}
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LabelError::InvalidName(name) => write!(f, "invalid label name: {:?}", name),
+            LabelError::ReservedName(name) => write!(f, "reserved label name: {:?}", name),
+            LabelError::DuplicateName(name) => write!(f, "duplicate label name: {:?}", name),
+        }
+    }
+}
+impl Error for LabelError {}
+
+fn is_valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Renders the metrics of the given nodes in Prometheus text exposition format.
+///
+/// The samples of each metrics family are grouped together,
+/// so the metrics of several nodes can be rendered into the same exposition.
+///
+/// # Examples
+///
+/// ```
+/// use hyparview::prometheus::{self, Labels};
+/// use hyparview::Node;
+///
+/// let foo = Node::new("foo", rand::thread_rng());
+/// let bar = Node::new("bar", rand::thread_rng());
+/// let text = prometheus::encode(&[
+///     (&Labels::new(&[("overlay", "foo")]).unwrap(), &foo.metrics()),
+///     (&Labels::new(&[("overlay", "bar")]).unwrap(), &bar.metrics()),
+/// ]);
+/// assert!(text.contains("hyparview_active_view_size{overlay=\"foo\"} 0\n"));
+/// ```
+pub fn encode(nodes: &[(&Labels, &NodeMetrics)]) -> String {
+    let mut buf = String::new();
+    write(&mut buf, nodes).expect("never fails");
+    buf
+}
+
+/// Writes the metrics of the given nodes in Prometheus text exposition format to `writer`.
+///
+/// See also [encode](./fn.encode.html).
+pub fn write<W: Write>(writer: &mut W, nodes: &[(&Labels, &NodeMetrics)]) -> fmt::Result {
+    let mut encoder = Encoder { writer };
+    encoder.write_family(
+        "received_messages_total",
+        "counter",
+        "Number of received messages.",
+        nodes,
+        |m| per_kind(&m.received_messages),
+    )?;
+    encoder.write_family(
+        "sent_messages_total",
+        "counter",
+        "Number of sent messages.",
+        nodes,
+        |m| per_kind(&m.sent_messages),
+    )?;
+    encoder.write_family(
+        "rejected_messages_total",
+        "counter",
+        "Number of received messages discarded without being handled.",
+        nodes,
+        |m| vec![(None, m.rejected_messages)],
+    )?;
+    encoder.write_family(
+        "active_view_evictions_total",
+        "counter",
+        "Number of nodes evicted from the active view.",
+        nodes,
+        |m| vec![(None, m.active_view_evictions)],
+    )?;
+    encoder.write_family(
+        "passive_view_evictions_total",
+        "counter",
+        "Number of nodes evicted from the passive view.",
+        nodes,
+        |m| vec![(None, m.passive_view_evictions)],
+    )?;
+    encoder.write_family(
+        "promotions_total",
+        "counter",
+        "Number of nodes moved from the passive view to the active view.",
+        nodes,
+        |m| vec![(None, m.promotions)],
+    )?;
+    encoder.write_family(
+        "active_view_size",
+        "gauge",
+        "Number of nodes in the active view.",
+        nodes,
+        |m| vec![(None, m.active_view_size as u64)],
+    )?;
+    encoder.write_family(
+        "passive_view_size",
+        "gauge",
+        "Number of nodes in the passive view.",
+        nodes,
+        |m| vec![(None, m.passive_view_size as u64)],
+    )?;
+    Ok(())
+}
+
+impl NodeMetrics {
+    /// Renders the metrics in Prometheus text exposition format.
+    ///
+    /// `labels` are attached to every sample.
+    /// Use [prometheus::encode](./prometheus/fn.encode.html) for rendering the metrics of several nodes.
+    pub fn to_prometheus_text(&self, labels: &Labels) -> String {
+        encode(&[(labels, self)])
+    }
+}
+
+type Samples = Vec<(Option<&'static str>, u64)>;
+
+struct Encoder<'a, W> {
+    writer: &'a mut W,
+}
+impl<W: Write> Encoder<'_, W> {
+    fn write_family<F>(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        nodes: &[(&Labels, &NodeMetrics)],
+        samples: F,
+    ) -> fmt::Result
+    where
+        F: Fn(&NodeMetrics) -> Samples,
+    {
+        writeln!(self.writer, "# HELP hyparview_{} {}", name, help)?;
+        writeln!(self.writer, "# TYPE hyparview_{} {}", name, kind)?;
+        for (labels, metrics) in nodes {
+            for (message_kind, value) in samples(metrics) {
+                write!(self.writer, "hyparview_{}", name)?;
+                self.write_labels(labels, message_kind)?;
+                writeln!(self.writer, " {}", value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_labels(&mut self, labels: &Labels, message_kind: Option<&str>) -> fmt::Result {
+        let message_kind = message_kind.map(|k| (Labels::RESERVED_KIND, k));
+        let labels = labels.pairs.iter().map(|(n, v)| (n.as_str(), v.as_str()));
+        let mut labels = labels.chain(message_kind).peekable();
+        if labels.peek().is_none() {
+            return Ok(());
+        }
+        self.writer.write_char('{')?;
+        for (i, (name, value)) in labels.enumerate() {
+            if i != 0 {
+                self.writer.write_char(',')?;
+            }
+            write!(self.writer, "{}=\"", name)?;
+            for c in value.chars() {
+                match c {
+                    '\\' => self.writer.write_str("\\\\")?,
+                    '"' => self.writer.write_str("\\\"")?,
+                    '\n' => self.writer.write_str("\\n")?,
+                    _ => self.writer.write_char(c)?,
+                }
+            }
+            self.writer.write_char('"')?;
+        }
+        self.writer.write_char('}')
+    }
+}
+
+fn per_kind(counters: &BTreeMap<MessageKind, u64>) -> Samples {
+    counters
+        .iter()
+        .map(|(kind, count)| (Some(kind_label(*kind)), *count))
+        .collect()
+}
+
+fn kind_label(kind: MessageKind) -> &'static str {
+    match kind {
+        MessageKind::Join => "join",
+        MessageKind::JoinAccept => "join_accept",
+        MessageKind::JoinRedirect => "join_redirect",
+        MessageKind::ForwardJoin => "forward_join",
+        MessageKind::Neighbor => "neighbor",
+        MessageKind::Shuffle => "shuffle",
+        MessageKind::ShuffleReply => "shuffle_reply",
+        MessageKind::Disconnect => "disconnect",
+        MessageKind::Optimization => "optimization",
+        MessageKind::OptimizationReply => "optimization_reply",
+        MessageKind::Replace => "replace",
+        MessageKind::ReplaceReply => "replace_reply",
+        MessageKind::Switch => "switch",
+        MessageKind::SwitchReply => "switch_reply",
+        MessageKind::TryLater => "try_later",
+        MessageKind::Ping => "ping",
+        MessageKind::Pong => "pong",
+    }
+}
@@ -0,0 +1,214 @@
+use crate::message::MessageKind;
+use crate::view::IndexedVec;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Token bucket based limit of incoming messages sent by a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RateLimit {
+    /// Maximum number of tokens in a bucket (i.e., the maximum burst size).
+    pub burst: u32,
+
+    /// Interval at which a token is added to a bucket.
+    ///
+    /// If it is zero, the messages are not limited.
+    #[cfg_attr(feature = "serde", serde(with = "crate::seconds"))]
+    pub refill_interval: Duration,
+}
+
+/// What to do with incoming messages that exceeded their rate limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RateLimitPolicy {
+    /// Discard the messages silently.
+    Drop,
+
+    /// Discard the messages and reply `TRY_LATER` messages to the senders.
+    TryLater,
+}
+
+/// Per-peer rate limits of incoming messages.
+///
+/// Each limit is applied to every (sender, message kind) pair individually.
+/// `None` means that there is no limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct RateLimitOptions {
+    /// Limit of `JOIN` messages.
+    pub join: Option<RateLimit>,
+
+    /// Limit of `FORWARD_JOIN` messages.
+    pub forward_join: Option<RateLimit>,
+
+    /// Limit of `SHUFFLE` messages.
+    pub shuffle: Option<RateLimit>,
+
+    /// What to do with the messages that exceeded the limits.
+    pub policy: RateLimitPolicy,
+
+    /// Maximum number of token buckets held by a node.
+    ///
+    /// A bucket is held for each (sender, message kind) pair until it is refilled completely.
+    /// If the limit is reached, limited messages from the senders without buckets are discarded
+    /// until some buckets are refilled and pruned by `Node::advance_clock`.
+    pub max_buckets: u16,
+}
+impl RateLimitOptions {
+    /// The default value of `max_buckets` field.
+    pub const DEFAULT_MAX_BUCKETS: u16 = 1024;
+
+    pub(crate) fn get(&self, kind: MessageKind) -> Option<&RateLimit> {
+        match kind {
+            MessageKind::Join => self.join.as_ref(),
+            MessageKind::ForwardJoin => self.forward_join.as_ref(),
+            MessageKind::Shuffle => self.shuffle.as_ref(),
+            _ => None,
+        }
+    }
+}
+impl Default for RateLimitOptions {
+    fn default() -> Self {
+        RateLimitOptions {
+            join: None,
+            forward_join: None,
+            shuffle: None,
+            policy: RateLimitPolicy::Drop,
+            max_buckets: Self::DEFAULT_MAX_BUCKETS,
+        }
+    }
+}
+
+/// Numbers of incoming messages discarded by rate limiting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// Number of discarded `JOIN` messages.
+    pub shed_joins: u64,
+
+    /// Number of discarded `FORWARD_JOIN` messages.
+    pub shed_forward_joins: u64,
+
+    /// Number of discarded `SHUFFLE` messages.
+    pub shed_shuffles: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct RateLimiter<T> {
+    buckets: IndexedVec<(T, MessageKind), ((T, MessageKind), TokenBucket)>,
+    stats: RateLimitStats,
+}
+impl<T> RateLimiter<T>
+where
+    T: Clone + Eq + Hash,
+{
+    pub fn new() -> Self {
+        RateLimiter {
+            buckets: IndexedVec::with_key(0, |b| &b.0),
+            stats: RateLimitStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &RateLimitStats {
+        &self.stats
+    }
+
+    /// Takes a token from the bucket of the given sender and message kind.
+    ///
+    /// If the bucket is empty (or there is no room for a new bucket),
+    /// returns the duration until the next token is available.
+    pub fn acquire(
+        &mut self,
+        sender: &T,
+        kind: MessageKind,
+        options: &RateLimitOptions,
+        now: Duration,
+    ) -> Result<(), Duration> {
+        let limit = match options.get(kind) {
+            Some(limit) if limit.refill_interval != Duration::from_secs(0) => limit,
+            _ => return Ok(()),
+        };
+        let key = (sender.clone(), kind);
+        let result = if let Some(bucket) = self.buckets.get_mut(&key) {
+            bucket.1.acquire(limit, now)
+        } else if self.buckets.len() < usize::from(options.max_buckets) {
+            let mut bucket = TokenBucket::new(limit, now);
+            let result = bucket.acquire(limit, now);
+            self.buckets.push((key, bucket));
+            result
+        } else {
+            Err(limit.refill_interval)
+        };
+        if result.is_err() {
+            match kind {
+                MessageKind::Join => self.stats.shed_joins += 1,
+                MessageKind::ForwardJoin => self.stats.shed_forward_joins += 1,
+                _ => self.stats.shed_shuffles += 1,
+            }
+        }
+        result
+    }
+
+    /// Removes the buckets that have been refilled completely.
+    pub fn prune(&mut self, options: &RateLimitOptions, now: Duration) {
+        self.buckets.retain(|b| {
+            options
+                .get((b.0).1)
+                .is_some_and(|limit| !b.1.is_full(limit, now))
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    tokens: u32,
+    last_refill: Duration,
+}
+impl TokenBucket {
+    pub fn new(limit: &RateLimit, now: Duration) -> Self {
+        TokenBucket {
+            tokens: limit.burst,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token from the bucket.
+    ///
+    /// If the bucket is empty, returns the duration until the next token is available.
+    pub fn acquire(&mut self, limit: &RateLimit, now: Duration) -> Result<(), Duration> {
+        if limit.refill_interval == Duration::from_secs(0) {
+            return Ok(());
+        }
+        self.refill(limit, now);
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            Ok(())
+        } else {
+            Err(limit.refill_interval - (now - self.last_refill))
+        }
+    }
+
+    fn is_full(&self, limit: &RateLimit, now: Duration) -> bool {
+        self.refilled(limit, now).0 >= limit.burst
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Duration) {
+        let (tokens, last_refill) = self.refilled(limit, now);
+        self.tokens = tokens;
+        self.last_refill = last_refill;
+    }
+
+    // Returns the number of tokens and the last refill time after refilling the bucket at `now`.
+    fn refilled(&self, limit: &RateLimit, now: Duration) -> (u32, Duration) {
+        let elapsed = now - self.last_refill;
+        let interval = limit.refill_interval.as_nanos().max(1);
+        let count = elapsed.as_nanos() / interval;
+        let tokens = u128::from(self.tokens) + count;
+        if tokens >= u128::from(limit.burst) {
+            (limit.burst, now)
+        } else {
+            let refilled = Duration::from_nanos((count * interval) as u64);
+            (tokens as u32, self.last_refill + refilled)
+        }
+    }
+}
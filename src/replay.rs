@@ -0,0 +1,324 @@
+//! Record-and-replay of the inputs of HyParView [Node](../struct.Node.html).
+//!
+//! [Recorder](./struct.Recorder.html) logs every input given to a node and every action polled from it
+//! to a trace file (JSON Lines).
+//! [replay](./fn.replay.html) re-executes the inputs of a trace using the same seeded RNG,
+//! and [Trace::diff](./struct.Trace.html#method.diff) reports the first divergence between two traces.
+//!
+//! This module is available only if the `replay` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use hyparview::replay::{self, Input, Recorder, Trace, TraceHeader};
+//! use hyparview::NodeOptions;
+//!
+//! let header = TraceHeader { node_id: "foo".to_owned(), seed: 1, options: NodeOptions::default() };
+//! let mut recorder = Recorder::new(header, Vec::new()).unwrap();
+//! recorder.input(Input::Join("bar".to_owned())).unwrap();
+//! while recorder.poll_action().unwrap().is_some() {}
+//!
+//! let (_, file) = recorder.into_inner();
+//! let trace = Trace::<String>::read_from(&file[..]).unwrap();
+//! let replayed = replay::replay(&trace, |_| {});
+//! assert_eq!(trace.diff(&replayed), None);
+//! ```
+use crate::message::ProtocolMessage;
+use crate::{Action, Node, NodeOptions, ReputationEvent, ZoneId};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+/// Node type used for recording and replaying.
+pub type ReplayableNode<T> = Node<T, StdRng>;
+
+/// Header of a trace, which is required for constructing the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceHeader<T> {
+    /// The ID of the node.
+    pub node_id: T,
+
+    /// The seed of the RNG used by the node.
+    pub seed: u64,
+
+    /// The options of the node.
+    pub options: NodeOptions,
+}
+
+/// Input given to a node.
+///
+/// Each variant corresponds to a method of [Node](../struct.Node.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input<T> {
+    /// `Node::set_zone` call.
+    SetZone(Option<ZoneId>),
+
+    /// `Node::set_incarnation` call.
+    SetIncarnation(u64),
+
+    /// `Node::join` call.
+    Join(T),
+
+    /// `Node::disconnect` call.
+    Disconnect {
+        /// The node to be disconnected.
+        node: T,
+
+        /// Whether the node is alive or not.
+        alive: bool,
+    },
+
+    /// `Node::handle_protocol_message` call.
+    HandleProtocolMessage(ProtocolMessage<T>),
+
+    /// `Node::advance_clock` call.
+    AdvanceClock(Duration),
+
+    /// `Node::shuffle_passive_view` call.
+    ShufflePassiveView,
+
+    /// `Node::fill_active_view` call.
+    FillActiveView,
+
+    /// `Node::sync_active_view` call.
+    SyncActiveView,
+
+    /// `Node::optimize_active_view` call.
+    OptimizeActiveView,
+
+    /// `Node::probe_passive_view` call.
+    ProbePassiveView,
+
+    /// `Node::report_unreachable` call.
+    ReportUnreachable(T),
+
+    /// `Node::record_reputation_event` call.
+    RecordReputationEvent {
+        /// The node for which the event was observed.
+        node: T,
+
+        /// The observed event.
+        event: ReputationEvent,
+    },
+
+    /// `Node::apply_options` call.
+    ///
+    /// Invalid options are ignored as the node is not changed by them.
+    ApplyOptions(NodeOptions),
+}
+impl<T> Input<T>
+where
+    T: Clone + Eq + Hash,
+{
+    /// Gives the input to `node`.
+    pub fn apply(self, node: &mut ReplayableNode<T>) {
+        match self {
+            Input::SetZone(zone) => node.set_zone(zone),
+            Input::SetIncarnation(incarnation) => node.set_incarnation(incarnation),
+            Input::Join(contact_node_id) => node.join(contact_node_id),
+            Input::Disconnect { node: n, alive } => node.disconnect(&n, alive),
+            Input::HandleProtocolMessage(message) => node.handle_protocol_message(message),
+            Input::AdvanceClock(elapsed) => node.advance_clock(elapsed),
+            Input::ShufflePassiveView => node.shuffle_passive_view(),
+            Input::FillActiveView => node.fill_active_view(),
+            Input::SyncActiveView => node.sync_active_view(),
+            Input::OptimizeActiveView => node.optimize_active_view(),
+            Input::ProbePassiveView => node.probe_passive_view(),
+            Input::ReportUnreachable(n) => node.report_unreachable(&n),
+            Input::RecordReputationEvent { node: n, event } => {
+                node.record_reputation_event(&n, event)
+            }
+            Input::ApplyOptions(options) => {
+                let _ = node.apply_options(options);
+            }
+        }
+    }
+}
+
+/// Entry of a trace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceEntry<T> {
+    /// An input given to the node.
+    Input(Input<T>),
+
+    /// The result of a `Node::poll_action` call.
+    Action(Option<Action<T>>),
+}
+
+/// Recorded trace of a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trace<T> {
+    /// The header of the trace.
+    pub header: TraceHeader<T>,
+
+    /// The recorded entries.
+    pub entries: Vec<TraceEntry<T>>,
+}
+impl<T> Trace<T>
+where
+    T: DeserializeOwned,
+{
+    /// Reads a trace file written by [Recorder](./struct.Recorder.html).
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "empty trace")),
+            Some(line) => serde_json::from_str(&line?)?,
+        };
+        let mut entries = Vec::new();
+        for line in lines {
+            entries.push(serde_json::from_str(&line?)?);
+        }
+        Ok(Trace { header, entries })
+    }
+}
+impl<T> Trace<T>
+where
+    T: Clone + PartialEq,
+{
+    /// Returns the first divergence between the entries of `self` (expected) and `other` (actual).
+    ///
+    /// If the entries are identical, returns `None`.
+    pub fn diff(&self, other: &Self) -> Option<Divergence<T>> {
+        let len = self.entries.len().max(other.entries.len());
+        (0..len).find_map(|i| {
+            let expected = self.entries.get(i);
+            let actual = other.entries.get(i);
+            if expected == actual {
+                None
+            } else {
+                Some(Divergence {
+                    index: i,
+                    expected: expected.cloned(),
+                    actual: actual.cloned(),
+                })
+            }
+        })
+    }
+}
+
+/// The first divergence between two traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence<T> {
+    /// The index of the divergent entry.
+    pub index: usize,
+
+    /// The expected entry (`None` means that the expected trace has ended).
+    pub expected: Option<TraceEntry<T>>,
+
+    /// The actual entry (`None` means that the actual trace has ended).
+    pub actual: Option<TraceEntry<T>>,
+}
+impl<T: fmt::Debug> fmt::Display for Divergence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "traces diverged at entry #{}", self.index)?;
+        writeln!(f, "- expected: {:?}", self.expected)?;
+        write!(f, "+ actual:   {:?}", self.actual)
+    }
+}
+
+/// Wrapper of a node that records its inputs and actions to a trace file.
+///
+/// Configurations applied by [configure](#method.configure) are not recorded,
+/// so the same configurations must be applied when replaying the trace.
+#[derive(Debug)]
+pub struct Recorder<T, W> {
+    node: ReplayableNode<T>,
+    writer: W,
+}
+impl<T, W> Recorder<T, W>
+where
+    T: Clone + Eq + Hash + Serialize,
+    W: Write,
+{
+    /// Makes a new `Recorder` instance and writes the header of the trace to `writer`.
+    pub fn new(header: TraceHeader<T>, writer: W) -> io::Result<Self> {
+        let mut this = Recorder {
+            node: make_node(&header),
+            writer,
+        };
+        this.write_line(&header)?;
+        Ok(this)
+    }
+
+    /// Returns a reference to the node.
+    pub fn node(&self) -> &ReplayableNode<T> {
+        &self.node
+    }
+
+    /// Applies unrecorded configurations (e.g., `Node::enable_xbot`) to the node.
+    pub fn configure<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut ReplayableNode<T>),
+    {
+        f(&mut self.node);
+    }
+
+    /// Records `input` and gives it to the node.
+    pub fn input(&mut self, input: Input<T>) -> io::Result<()> {
+        self.write_line(&TraceEntry::Input(input.clone()))?;
+        input.apply(&mut self.node);
+        Ok(())
+    }
+
+    /// Polls an action from the node and records it.
+    pub fn poll_action(&mut self) -> io::Result<Option<Action<T>>> {
+        let action = self.node.poll_action();
+        self.write_line(&TraceEntry::Action(action.clone()))?;
+        Ok(action)
+    }
+
+    /// Returns the node and the writer.
+    pub fn into_inner(self) -> (ReplayableNode<T>, W) {
+        (self.node, self.writer)
+    }
+
+    fn write_line<V: Serialize>(&mut self, value: &V) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+/// Re-executes the inputs of `trace` and returns the resulting trace.
+///
+/// `configure` is applied to the node before the execution
+/// (it should be the same as the one given to `Recorder::configure`).
+///
+/// For finding the first divergence from the recorded trace, use `Trace::diff` method.
+pub fn replay<T, F>(trace: &Trace<T>, configure: F) -> Trace<T>
+where
+    T: Clone + Eq + Hash,
+    F: FnOnce(&mut ReplayableNode<T>),
+{
+    let mut node = make_node(&trace.header);
+    configure(&mut node);
+    let mut entries = Vec::with_capacity(trace.entries.len());
+    for entry in &trace.entries {
+        match entry {
+            TraceEntry::Input(input) => {
+                input.clone().apply(&mut node);
+                entries.push(entry.clone());
+            }
+            TraceEntry::Action(_) => {
+                entries.push(TraceEntry::Action(node.poll_action()));
+            }
+        }
+    }
+    Trace {
+        header: trace.header.clone(),
+        entries,
+    }
+}
+
+fn make_node<T>(header: &TraceHeader<T>) -> ReplayableNode<T>
+where
+    T: Clone + Eq + Hash,
+{
+    let rng = StdRng::seed_from_u64(header.seed);
+    Node::with_options(header.node_id.clone(), rng, header.options.clone())
+}
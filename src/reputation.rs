@@ -0,0 +1,165 @@
+use crate::view::IndexedVec;
+use std::fmt;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Observable signal that changes the reputation score of a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReputationEvent {
+    /// The peer has stayed in the active view for the given duration.
+    ActiveUptime(Duration),
+
+    /// The instance failed to send a message to the peer.
+    FailedSend,
+
+    /// The peer rejected a `NEIGHBOR` request sent by the instance.
+    RejectedNeighbor,
+
+    /// The peer sent a message exceeding the rate limits.
+    ///
+    /// This may happen to honest peers (e.g., relaying many `FORWARD_JOIN` messages during a restart storm),
+    /// so it should be weighted lighter than `InvalidMessage`.
+    RateLimited,
+
+    /// The peer sent an invalid message (e.g., a message that could not be decoded).
+    InvalidMessage,
+}
+
+/// Weighting of [ReputationEvent](./enum.ReputationEvent.html)s.
+pub trait ReputationWeighting {
+    /// Returns the amount by which `event` changes the reputation score of a peer.
+    fn weight(&mut self, event: &ReputationEvent) -> i64;
+}
+impl<F> ReputationWeighting for F
+where
+    F: FnMut(&ReputationEvent) -> i64,
+{
+    fn weight(&mut self, event: &ReputationEvent) -> i64 {
+        self(event)
+    }
+}
+
+/// Linear weighting of [ReputationEvent](./enum.ReputationEvent.html)s.
+#[derive(Debug, Clone)]
+pub struct ReputationWeights {
+    /// Weight of every second spent in the active view.
+    pub active_uptime_per_sec: i64,
+
+    /// Weight of a `ReputationEvent::FailedSend` event.
+    pub failed_send: i64,
+
+    /// Weight of a `ReputationEvent::RejectedNeighbor` event.
+    pub rejected_neighbor: i64,
+
+    /// Weight of a `ReputationEvent::RateLimited` event.
+    pub rate_limited: i64,
+
+    /// Weight of a `ReputationEvent::InvalidMessage` event.
+    pub invalid_message: i64,
+}
+impl ReputationWeights {
+    /// The default value of `active_uptime_per_sec` field.
+    pub const DEFAULT_ACTIVE_UPTIME_PER_SEC: i64 = 1;
+
+    /// The default value of `failed_send` field.
+    pub const DEFAULT_FAILED_SEND: i64 = -30;
+
+    /// The default value of `rejected_neighbor` field.
+    pub const DEFAULT_REJECTED_NEIGHBOR: i64 = -5;
+
+    /// The default value of `rate_limited` field.
+    pub const DEFAULT_RATE_LIMITED: i64 = -2;
+
+    /// The default value of `invalid_message` field.
+    pub const DEFAULT_INVALID_MESSAGE: i64 = -60;
+}
+impl Default for ReputationWeights {
+    fn default() -> Self {
+        ReputationWeights {
+            active_uptime_per_sec: Self::DEFAULT_ACTIVE_UPTIME_PER_SEC,
+            failed_send: Self::DEFAULT_FAILED_SEND,
+            rejected_neighbor: Self::DEFAULT_REJECTED_NEIGHBOR,
+            rate_limited: Self::DEFAULT_RATE_LIMITED,
+            invalid_message: Self::DEFAULT_INVALID_MESSAGE,
+        }
+    }
+}
+impl ReputationWeighting for ReputationWeights {
+    fn weight(&mut self, event: &ReputationEvent) -> i64 {
+        match event {
+            ReputationEvent::ActiveUptime(d) => {
+                let millis = d.as_millis().min(i64::MAX as u128) as i64;
+                self.active_uptime_per_sec.saturating_mul(millis) / 1000
+            }
+            ReputationEvent::FailedSend => self.failed_send,
+            ReputationEvent::RejectedNeighbor => self.rejected_neighbor,
+            ReputationEvent::RateLimited => self.rate_limited,
+            ReputationEvent::InvalidMessage => self.invalid_message,
+        }
+    }
+}
+
+pub(crate) struct Reputation<T> {
+    pub weighting: Box<dyn ReputationWeighting + Send + Sync>,
+
+    // (node, score)
+    scores: IndexedVec<T, (T, i64)>,
+
+    // The nodes to which the last low priority `NEIGHBOR` messages were sent.
+    //
+    // If one of them replies `DISCONNECT` message before joining the active view, the request has been rejected.
+    pub promotions: Vec<T>,
+
+    // Elapsed time that has not been reported as `ActiveUptime` events yet.
+    pub uptime_carry: Duration,
+}
+impl<T> Reputation<T>
+where
+    T: Clone + Eq + Hash,
+{
+    pub fn new(weighting: Box<dyn ReputationWeighting + Send + Sync>) -> Self {
+        Reputation {
+            weighting,
+            scores: IndexedVec::with_key(0, |x| &x.0),
+            promotions: Vec::new(),
+            uptime_carry: Duration::from_secs(0),
+        }
+    }
+
+    pub fn score(&self, node: &T) -> Option<i64> {
+        self.scores.get(node).map(|x| x.1)
+    }
+
+    /// Adds `weight` to the score of `node`.
+    ///
+    /// If there are more than `max_len` scores, the score closest to zero is evicted
+    /// (it may be the new score of `node`).
+    pub fn add_score(&mut self, node: &T, weight: i64, max_len: usize) {
+        if let Some(x) = self.scores.get_mut(node) {
+            x.1 = x.1.saturating_add(weight);
+        } else if weight != 0 {
+            self.scores.push((node.clone(), weight));
+            self.truncate(max_len);
+        }
+    }
+
+    /// Evicts the scores closest to zero until at most `max_len` scores remain.
+    pub fn truncate(&mut self, max_len: usize) {
+        while self.scores.len() > max_len {
+            let closest_to_zero = (0..self.scores.len())
+                .min_by_key(|&i| self.scores[i].1.unsigned_abs())
+                .expect("never fails");
+            self.scores.swap_remove(closest_to_zero);
+        }
+    }
+}
+impl<T: fmt::Debug> fmt::Debug for Reputation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reputation")
+            .field("scores", &self.scores)
+            .field("promotions", &self.promotions)
+            .field("uptime_carry", &self.uptime_carry)
+            .finish()
+    }
+}
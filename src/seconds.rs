@@ -0,0 +1,41 @@
+//! Serialization of durations as (fractional) numbers of seconds.
+use serde::{de, Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+pub fn serialize<S: Serializer>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(d.as_secs_f64())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let secs = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(secs).map_err(|_| {
+        de::Error::custom(format!(
+            "expected a non-negative number of seconds representable as a duration, found {}",
+            secs
+        ))
+    })
+}
+
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        d: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match d {
+            None => serializer.serialize_none(),
+            Some(d) => serializer.serialize_some(&d.as_secs_f64()),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        #[derive(Deserialize)]
+        struct Seconds(#[serde(with = "crate::seconds")] Duration);
+
+        Ok(Option::<Seconds>::deserialize(deserializer)?.map(|s| s.0))
+    }
+}
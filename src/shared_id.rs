@@ -0,0 +1,315 @@
+use crate::GroupKey;
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::Map;
+use std::ops::{Deref, Index};
+use std::slice;
+use std::sync::Arc;
+
+const MIN_PURGE_THRESHOLD: usize = 64;
+
+/// Node ID shared by reference counting.
+///
+/// [Node](./struct.Node.html) interns the IDs it receives and holds them as `SharedId`s internally,
+/// so copying IDs between its views and queued actions never clones the full IDs.
+/// The full IDs are cloned only when `Node::poll_action` or `Node::drain_actions` returns actions.
+///
+/// If the IDs still need to be shared outside of a node
+/// (e.g., by connection tables of the transport), `Node<SharedId<T>>` can also be used.
+/// Then, the returned actions contain pointer copies instead of clones of the full IDs.
+///
+/// Use [IdInterner](./struct.IdInterner.html) for sharing the same allocation among
+/// the IDs decoded from incoming messages.
+///
+/// # Examples
+///
+/// ```
+/// use hyparview::{Node, SharedId};
+///
+/// #[derive(Debug, PartialEq, Eq, Hash)]
+/// struct PeerId {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let id = SharedId::new(PeerId { host: "foo".to_owned(), port: 3000 });
+/// let mut node = Node::new(id, rand::thread_rng());
+/// node.join(SharedId::new(PeerId { host: "bar".to_owned(), port: 3000 }));
+///
+/// let action = node.poll_action().unwrap();
+/// if let hyparview::Action::Send { destination, .. } = action {
+///     assert_eq!(destination.host, "bar");
+/// }
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedId<T>(Arc<T>);
+impl<T> SharedId<T> {
+    /// Makes a new `SharedId` instance.
+    pub fn new(id: T) -> Self {
+        SharedId(Arc::new(id))
+    }
+
+    /// Returns `true` if `this` and `other` share the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// Returns the wrapped ID.
+    ///
+    /// The ID is cloned if it is shared by other instances.
+    pub fn into_inner(self) -> T
+    where
+        T: Clone,
+    {
+        Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
+    }
+}
+impl<T> Clone for SharedId<T> {
+    fn clone(&self) -> Self {
+        SharedId(Arc::clone(&self.0))
+    }
+}
+impl<T> From<T> for SharedId<T> {
+    fn from(id: T) -> Self {
+        SharedId::new(id)
+    }
+}
+impl<T> Deref for SharedId<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> AsRef<T> for SharedId<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> Borrow<T> for SharedId<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+impl<T: fmt::Debug> fmt::Debug for SharedId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl<T: fmt::Display> fmt::Display for SharedId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl<T: GroupKey> GroupKey for SharedId<T> {
+    fn group_key(&self) -> u64 {
+        self.0.group_key()
+    }
+}
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for SharedId<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for SharedId<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(SharedId::new)
+    }
+}
+
+/// Interner of node IDs.
+///
+/// It maps equal IDs (e.g., the IDs decoded from incoming messages) to the same [SharedId](./struct.SharedId.html),
+/// so that each distinct ID is allocated only once.
+pub struct IdInterner<T> {
+    ids: HashSet<SharedId<T>>,
+    purge_threshold: usize,
+}
+impl<T> IdInterner<T>
+where
+    T: Hash + Eq,
+{
+    /// Makes a new `IdInterner` instance.
+    pub fn new() -> Self {
+        IdInterner {
+            ids: HashSet::new(),
+            purge_threshold: MIN_PURGE_THRESHOLD,
+        }
+    }
+
+    /// Returns the shared ID equal to `id`.
+    pub fn intern(&mut self, id: T) -> SharedId<T> {
+        if let Some(shared) = self.ids.get(&id) {
+            return shared.clone();
+        }
+        let shared = SharedId::new(id);
+        self.ids.insert(shared.clone());
+        shared
+    }
+
+    /// Returns the shared ID equal to `id` if it has been interned.
+    pub fn get(&self, id: &T) -> Option<&SharedId<T>> {
+        self.ids.get(id)
+    }
+
+    /// Returns the number of the interned IDs.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if there are no interned IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Forgets the IDs that are not referred from anywhere other than the interner.
+    ///
+    /// This method should be invoked periodically to bound the memory usage.
+    pub fn purge(&mut self) {
+        self.ids.retain(|id| Arc::strong_count(&id.0) > 1);
+        self.purge_threshold = (self.ids.len() * 2).max(MIN_PURGE_THRESHOLD);
+    }
+
+    /// Purges the interner if it has doubled in size since the last purge.
+    ///
+    /// Thus, the amortized cost of purging is constant per interned ID.
+    pub(crate) fn purge_if_grown(&mut self) {
+        if self.ids.len() >= self.purge_threshold {
+            self.purge();
+        }
+    }
+}
+// The iteration order of the `HashSet` is not deterministic, so the IDs are omitted
+// (model checking fingerprints nodes by their `Debug` representations).
+impl<T> fmt::Debug for IdInterner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IdInterner").finish_non_exhaustive()
+    }
+}
+impl<T> Default for IdInterner<T>
+where
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Node IDs in a view of [Node](./struct.Node.html).
+///
+/// The IDs are held as `SharedId`s by the node, and this borrows them as `&T`.
+/// Use `Ids::shared_ids` for cloning the IDs cheaply.
+pub struct Ids<'a, T> {
+    ids: &'a [SharedId<T>],
+}
+impl<'a, T> Ids<'a, T> {
+    pub(crate) fn new(ids: &'a [SharedId<T>]) -> Self {
+        Ids { ids }
+    }
+
+    /// Returns the number of the IDs.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if there are no IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the `i`-th ID.
+    pub fn get(&self, i: usize) -> Option<&'a T> {
+        self.ids.get(i).map(|id| &**id)
+    }
+
+    /// Returns `true` if `id` is contained.
+    pub fn contains(&self, id: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.ids.iter().any(|x| **x == *id)
+    }
+
+    /// Returns an iterator over the IDs.
+    pub fn iter(&self) -> IdsIter<'a, T> {
+        self.into_iter()
+    }
+
+    /// Returns the IDs as `SharedId`s.
+    pub fn shared_ids(&self) -> &'a [SharedId<T>] {
+        self.ids
+    }
+
+    /// Clones the IDs into a `Vec`.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+}
+impl<'a, T> Clone for Ids<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for Ids<'a, T> {}
+impl<'a, T> Index<usize> for Ids<'a, T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        &self.ids[i]
+    }
+}
+impl<'a, T> IntoIterator for Ids<'a, T> {
+    type Item = &'a T;
+    type IntoIter = IdsIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ids.iter().map(SharedId::deref)
+    }
+}
+impl<'a, 'b, T, U> PartialEq<Ids<'b, U>> for Ids<'a, T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &Ids<'b, U>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+impl<'a, T, U> PartialEq<[U]> for Ids<'a, T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &[U]) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+impl<'a, T, U, const N: usize> PartialEq<[U; N]> for Ids<'a, T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &[U; N]) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+impl<'a, T, U> PartialEq<Vec<U>> for Ids<'a, T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &Vec<U>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+impl<'a, T: fmt::Debug> fmt::Debug for Ids<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the IDs of [Ids](./struct.Ids.html).
+pub type IdsIter<'a, T> = Map<slice::Iter<'a, SharedId<T>>, fn(&'a SharedId<T>) -> &'a T>;
@@ -0,0 +1,96 @@
+use crate::message::Tombstone;
+use crate::view::IndexedVec;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Time-limited set of nodes regarded as dead.
+#[derive(Debug)]
+pub(crate) struct Tombstones<T> {
+    // (node, incarnation, expiry time)
+    entries: IndexedVec<T, (T, u64, Duration)>,
+}
+impl<T> Tombstones<T>
+where
+    T: Clone + Eq + Hash,
+{
+    pub fn new() -> Self {
+        Tombstones {
+            entries: IndexedVec::with_key(0, |x| &x.0),
+        }
+    }
+
+    /// Records a tombstone of `node`.
+    ///
+    /// If there are already `max_len` tombstones, the one expiring first is replaced
+    /// (the new tombstone is discarded if it would expire first).
+    pub fn bury(&mut self, node: T, incarnation: u64, expiry_time: Duration, max_len: usize) {
+        if let Some(x) = self.entries.get_mut(&node) {
+            if x.1 < incarnation {
+                x.1 = incarnation;
+                x.2 = expiry_time;
+            } else if x.1 == incarnation {
+                x.2 = x.2.max(expiry_time);
+            }
+            return;
+        }
+        if self.entries.len() >= max_len {
+            let first_expiring = (0..self.entries.len()).min_by_key(|&i| self.entries[i].2);
+            match first_expiring {
+                Some(i) if self.entries[i].2 < expiry_time => {
+                    self.entries.swap_remove(i);
+                }
+                _ => return,
+            }
+        }
+        self.entries.push((node, incarnation, expiry_time));
+    }
+
+    pub fn unbury(&mut self, node: &T) {
+        if let Some(i) = self.entries.position(node) {
+            self.entries.swap_remove(i);
+        }
+    }
+
+    /// Returns `true` if the given incarnation of `node` is regarded as dead.
+    ///
+    /// An unknown incarnation (`None`) is regarded as dead if there is a tombstone for `node`.
+    pub fn is_buried(&self, node: &T, incarnation: Option<u64>, now: Duration) -> bool {
+        self.entries
+            .get(node)
+            .is_some_and(|x| now < x.2 && incarnation.is_none_or(|incarnation| incarnation <= x.1))
+    }
+
+    /// Removes the expired tombstones.
+    pub fn prune(&mut self, now: Duration) {
+        self.entries.retain(|x| now < x.2);
+    }
+
+    /// Removes the tombstones expiring first until at most `max_len` tombstones remain.
+    pub fn truncate(&mut self, max_len: usize) {
+        while self.entries.len() > max_len {
+            let first_expiring = (0..self.entries.len())
+                .min_by_key(|&i| self.entries[i].2)
+                .expect("never fails");
+            self.entries.swap_remove(first_expiring);
+        }
+    }
+
+    /// Returns at most `limit` tombstones, newest first, for gossiping.
+    pub fn select_for_gossip(&self, limit: usize, now: Duration) -> Vec<Tombstone<T>> {
+        let mut entries = self
+            .entries
+            .iter()
+            .filter(|x| now < x.2)
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|x| std::cmp::Reverse(x.2));
+        entries
+            .into_iter()
+            .take(limit)
+            .map(|x| Tombstone {
+                node: x.0.clone(),
+                incarnation: x.1,
+                lifetime: x.2 - now,
+            })
+            .collect()
+    }
+}
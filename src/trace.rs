@@ -0,0 +1,60 @@
+#[cfg(feature = "tracing")]
+use crate::SharedId;
+#[cfg(feature = "tracing")]
+use std::fmt;
+
+/// Function used for recording node IDs in spans and events.
+#[cfg(feature = "tracing")]
+pub(crate) type IdFormatter<T> = fn(&T, &mut fmt::Formatter) -> fmt::Result;
+
+/// Node ID recorded in spans and events.
+///
+/// If no formatter has been set by `Node::enable_id_tracing`, the ID is recorded as `_`.
+#[cfg(feature = "tracing")]
+pub(crate) struct TracedId<'a, T> {
+    pub id: &'a T,
+    pub formatter: Option<IdFormatter<T>>,
+}
+#[cfg(feature = "tracing")]
+impl<'a, T> fmt::Debug for TracedId<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.formatter {
+            Some(formatter) => formatter(self.id, f),
+            None => f.write_str("_"),
+        }
+    }
+}
+
+/// Node IDs recorded in spans and events.
+#[cfg(feature = "tracing")]
+pub(crate) struct TracedIds<'a, T> {
+    pub ids: &'a [SharedId<T>],
+    pub formatter: Option<IdFormatter<T>>,
+}
+#[cfg(feature = "tracing")]
+impl<'a, T> fmt::Debug for TracedIds<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.ids.iter().map(|id| TracedId {
+                id: &**id,
+                formatter: self.formatter,
+            }))
+            .finish()
+    }
+}
+
+/// Enters a `DEBUG` level span until the end of the current block if the `tracing` feature is enabled.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emits a `DEBUG` level event if the `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
@@ -5,15 +5,16 @@
 /// the message will be handled by the node that keeps the message at the time.
 /// So, a TTL can be regarded as the hop count of a message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct TimeToLive(u8);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeToLive(u16);
 impl TimeToLive {
     /// Makes a new `TimeToLive` instance.
-    pub fn new(ttl: u8) -> Self {
+    pub fn new(ttl: u16) -> Self {
         TimeToLive(ttl)
     }
 
     /// Returns the value of the TTL.
-    pub fn as_u8(self) -> u8 {
+    pub fn as_u16(self) -> u16 {
         self.0
     }
 
@@ -0,0 +1,153 @@
+use rand::Rng;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+
+/// Active or passive view.
+pub(crate) type View<T> = IndexedVec<T, T>;
+
+/// `Vec` of entries identified by node IDs.
+///
+/// The entries are stored in a `Vec` for random sampling,
+/// and a hash index from the IDs to the positions of the entries makes lookups by IDs constant time.
+pub(crate) struct IndexedVec<K, V> {
+    entries: Vec<V>,
+    key: fn(&V) -> &K,
+    index: HashMap<K, usize>,
+}
+impl<K> IndexedVec<K, K>
+where
+    K: Clone + Eq + Hash,
+{
+    pub fn with_capacity(capacity: usize) -> Self {
+        IndexedVec::with_key(capacity, |k| k)
+    }
+}
+impl<K, V> IndexedVec<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    pub fn with_key(capacity: usize, key: fn(&V) -> &K) -> Self {
+        IndexedVec {
+            entries: Vec::with_capacity(capacity),
+            key,
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.contains_key(key)
+    }
+
+    pub fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.get(key).copied()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.position(key).map(|i| &self.entries[i])
+    }
+
+    /// Note that the key of the resulting entry must not be changed.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.position(key).map(move |i| &mut self.entries[i])
+    }
+
+    /// Note that the key of the resulting entry must not be changed.
+    pub fn get_index_mut(&mut self, i: usize) -> Option<&mut V> {
+        self.entries.get_mut(i)
+    }
+
+    /// Note that the key of `entry` must not be contained.
+    pub fn push(&mut self, entry: V) {
+        let old = self
+            .index
+            .insert((self.key)(&entry).clone(), self.entries.len());
+        debug_assert!(old.is_none());
+        self.entries.push(entry);
+    }
+
+    pub fn swap_remove(&mut self, i: usize) -> V {
+        let entry = self.entries.swap_remove(i);
+        self.index.remove((self.key)(&entry));
+        if let Some(moved) = self.entries.get(i) {
+            self.index.insert((self.key)(moved).clone(), i);
+        }
+        entry
+    }
+
+    pub fn swap(&mut self, i: usize, j: usize) {
+        self.entries.swap(i, j);
+        self.index.insert((self.key)(&self.entries[i]).clone(), i);
+        self.index.insert((self.key)(&self.entries[j]).clone(), j);
+    }
+
+    /// Removes the entries for which `f` returns `false`.
+    ///
+    /// Note that the order of the remaining entries is not preserved.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut i = 0;
+        while i < self.entries.len() {
+            if f(&self.entries[i]) {
+                i += 1;
+            } else {
+                self.swap_remove(i);
+            }
+        }
+    }
+
+    /// Moves `amount` randomly selected entries to the front.
+    ///
+    /// Only the moved entries are reindexed.
+    pub fn partial_shuffle<R: Rng>(&mut self, rng: &mut R, amount: usize) {
+        for i in 0..amount.min(self.entries.len()) {
+            let j = rng.gen_range(i, self.entries.len());
+            if i != j {
+                self.swap(i, j);
+            }
+        }
+    }
+}
+impl<K, V: fmt::Debug> fmt::Debug for IndexedVec<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The index is omitted because the iteration order of `HashMap` is not deterministic.
+        f.debug_struct("IndexedVec")
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+impl<K, V> Deref for IndexedVec<K, V> {
+    type Target = [V];
+
+    fn deref(&self) -> &[V] {
+        &self.entries
+    }
+}
+impl<'a, K, V> IntoIterator for &'a IndexedVec<K, V> {
+    type Item = &'a V;
+    type IntoIter = std::slice::Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
@@ -0,0 +1,106 @@
+use crate::SharedId;
+use std::fmt;
+
+/// Oracle that estimates the cost of links used by the [X-BOT] extension.
+///
+/// The cost can be any metric that should be minimized (e.g., RTT or inter-datacenter traffic charges).
+///
+/// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+pub trait LinkCostOracle<T> {
+    /// Returns the cost of the link between the local node and `node`.
+    fn link_cost(&mut self, node: &T) -> u64;
+}
+impl<T, F> LinkCostOracle<T> for F
+where
+    F: FnMut(&T) -> u64,
+{
+    fn link_cost(&mut self, node: &T) -> u64 {
+        self(node)
+    }
+}
+
+/// Options for the [X-BOT] extension of HyParView [Node](./struct.Node.html).
+///
+/// [X-BOT]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+#[derive(Debug, Clone)]
+pub struct XbotOptions {
+    /// Number of active view members that are never replaced by optimization.
+    ///
+    /// These neighbors are selected by the original HyParView algorithm and
+    /// keep the random properties of the overlay.
+    pub unbiased_neighbors: u8,
+
+    /// Protocol parameter that is called `PSL` (passive scan length) in the [paper].
+    ///
+    /// This is the number of passive view members evaluated in each optimization round.
+    ///
+    /// [paper]: https://asc.di.fct.unl.pt/~jleitao/pdf/srds09-leitao.pdf
+    pub passive_scan_len: u8,
+}
+impl XbotOptions {
+    /// The default value of `unbiased_neighbors` field.
+    pub const DEFAULT_UNBIASED_NEIGHBORS: u8 = 1;
+
+    /// The default value of `passive_scan_len` field.
+    pub const DEFAULT_PASSIVE_SCAN_LEN: u8 = 2;
+}
+impl Default for XbotOptions {
+    fn default() -> Self {
+        XbotOptions {
+            unbiased_neighbors: Self::DEFAULT_UNBIASED_NEIGHBORS,
+            passive_scan_len: Self::DEFAULT_PASSIVE_SCAN_LEN,
+        }
+    }
+}
+
+pub(crate) struct Xbot<T> {
+    pub oracle: Box<dyn LinkCostOracle<T> + Send + Sync>,
+    pub options: XbotOptions,
+
+    // Active view members added by optimization.
+    pub optimized: Vec<SharedId<T>>,
+
+    // Outstanding `OPTIMIZATION` requests: (candidate, old_node).
+    //
+    // At most one request is outstanding for each active view member.
+    pub pending: Vec<(SharedId<T>, SharedId<T>)>,
+}
+impl<T> Xbot<T> {
+    pub fn new(oracle: Box<dyn LinkCostOracle<T> + Send + Sync>, options: XbotOptions) -> Self {
+        Xbot {
+            oracle,
+            options,
+            optimized: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+}
+impl<T: Eq> Xbot<T> {
+    pub fn start_optimization(&mut self, candidate: SharedId<T>, old_node: SharedId<T>) {
+        self.pending.retain(|x| x.1 != old_node);
+        self.pending.push((candidate, old_node));
+    }
+
+    /// Returns `true` if there was an outstanding request that matches the reply.
+    pub fn finish_optimization(&mut self, candidate: &SharedId<T>, old_node: &SharedId<T>) -> bool {
+        let position = self
+            .pending
+            .iter()
+            .position(|x| x.0 == *candidate && x.1 == *old_node);
+        if let Some(i) = position {
+            self.pending.swap_remove(i);
+            true
+        } else {
+            false
+        }
+    }
+}
+impl<T: fmt::Debug> fmt::Debug for Xbot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Xbot")
+            .field("options", &self.options)
+            .field("optimized", &self.optimized)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
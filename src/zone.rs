@@ -0,0 +1,18 @@
+/// Identifier of the zone (e.g., availability zone) to which a node belongs.
+///
+/// Zones are used for mixing local and remote peers in the active view
+/// (see the `max_*_zone_active_view_size` fields of [NodeOptions](./struct.NodeOptions.html)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZoneId(u32);
+impl ZoneId {
+    /// Makes a new `ZoneId` instance.
+    pub fn new(id: u32) -> Self {
+        ZoneId(id)
+    }
+
+    /// Returns the value of the ID.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}